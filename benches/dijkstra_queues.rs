@@ -0,0 +1,66 @@
+//! Compares the running time of Dijkstra with each of the vertex queues on grid graphs with
+//! small integer weights.  Run with `cargo bench --bench dijkstra_queues`
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use short::{DirectedGraph, Dijkstra, GraphBuilder, MinMax, VertexQueue, LazyBinaryHeap, DialQueue, RadixHeap};
+
+const RUNS: u32 = 5;
+
+// size x size grid with edges both ways between neighbors, weighted 1 to max_weight
+fn grid_graph(size: usize, max_weight: i64) -> DirectedGraph {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut g = DirectedGraph::new();
+    for row in 0..size {
+        for col in 0..size {
+            let v = row * size + col + 1;
+            if col + 1 < size {
+                (&mut g).add_edge(v, v + 1, rng.gen_range(1..=max_weight));
+                (&mut g).add_edge(v + 1, v, rng.gen_range(1..=max_weight));
+            }
+            if row + 1 < size {
+                (&mut g).add_edge(v, v + size, rng.gen_range(1..=max_weight));
+                (&mut g).add_edge(v + size, v, rng.gen_range(1..=max_weight));
+            }
+        }
+    }
+    g
+}
+
+// average time of a search from vertex 1 with a new queue made by make_queue, along with the
+// total of the distances found (to check the queues agree)
+fn time_queue<Q: VertexQueue, F: Fn() -> Q>(g: &DirectedGraph, make_queue: F) -> (Duration, i64) {
+    let mut total = Duration::ZERO;
+    let mut checksum = 0;
+    for _ in 0..RUNS {
+        let mut d = Dijkstra::with_queue(1, make_queue());
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        let start = Instant::now();
+        d.calculate_shortest_paths(g, 1).unwrap();
+        total += start.elapsed();
+        checksum = d.get_shortest_path_distances().values()
+            .map(|distance| match distance { MinMax::Value(distance) => *distance, _ => 0 })
+            .sum();
+    }
+    (total / RUNS, checksum)
+}
+
+fn main() {
+    for (size, max_weight) in [(100, 10), (200, 10), (200, 1000)] {
+        let g = grid_graph(size, max_weight);
+        println!("{} x {} grid, weights 1-{}: {} vertexes, {} edges", size, size, max_weight, g.vertex_count(), g.edge_count());
+        let results = [
+            ("min-heap", time_queue(&g, minheap::MinHeap::new)),
+            ("lazy-heap", time_queue(&g, LazyBinaryHeap::new)),
+            ("dial", time_queue(&g, DialQueue::new)),
+            ("radix", time_queue(&g, RadixHeap::new)),
+        ];
+        for (name, (time, checksum)) in results {
+            assert_eq!(checksum, results[0].1.1, "{} distances differ", name);
+            println!("  {:<10} {:>10.2?}", name, time);
+        }
+    }
+}
@@ -0,0 +1,510 @@
+extern crate minheap;
+use std::collections::{BTreeMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use minheap::MinHeap;
+use rand::seq::SliceRandom;
+use regex::Regex;
+
+use crate::dirgraph::DirectedGraph;
+use crate::graphbuilder::GraphBuilder;
+use crate::dijkstra::Dijkstra;
+
+use log::{ info, debug, error, warn, trace };
+use crate::minmax::{MinMax,MinMax::Value};
+use crate::shortpathinfo::ShortestPathInfo;
+use crate::parse::parse_field;
+use crate::error::{Error, Result};
+
+/// Estimate of the remaining distance from a vertex to the target used to guide the A* search.
+/// The estimate must never be more than the actual distance for A* to find the shortest path
+pub trait Heuristic {
+    fn estimate(&self, vertex: usize, target: usize) -> i64;
+}
+
+/// Heuristic that always estimates 0, which makes A* equivalent to Dijkstra
+#[derive(Debug,Clone,Default)]
+pub struct ZeroHeuristic;
+
+impl Heuristic for ZeroHeuristic {
+    fn estimate(&self, _vertex: usize, _target: usize) -> i64 {
+        0
+    }
+}
+
+/// How the landmarks of a LandmarkHeuristic are chosen
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum LandmarkSelection {
+    /// each landmark is the vertex farthest from the landmarks already chosen
+    Farthest(usize),
+    /// landmarks chosen at random
+    Random(usize),
+    /// the given vertexes
+    Given(Vec<usize>),
+}
+
+/// Heuristic based on the distances to and from a set of landmark vertexes (ALT).  By the
+/// triangle inequality the distance from v to t is at least dist(L,t) - dist(L,v) and
+/// dist(v,L) - dist(t,L) for each landmark L
+#[derive(Debug,Clone)]
+pub struct LandmarkHeuristic {
+    landmarks: Vec<usize>,
+    /// shortest path distances from each landmark to every vertex
+    distances: Vec<BTreeMap<usize,MinMax<i64>>>,
+    /// shortest path distances from every vertex to each landmark
+    backward_distances: Vec<BTreeMap<usize,MinMax<i64>>>,
+}
+
+impl LandmarkHeuristic {
+
+    /// Calculates the distances to and from each of the landmarks using Dijkstra (so the graph
+    /// must not have negative edge weights)
+    pub fn new(graph: &DirectedGraph, landmarks: &[usize]) -> Result<LandmarkHeuristic> {
+        let mut heuristic = LandmarkHeuristic::from_tables(Vec::new(), Vec::new(), Vec::new());
+        let reversed = reversed_graph(graph);
+        for landmark in landmarks {
+            heuristic.add_landmark(graph, &reversed, *landmark)?;
+        }
+        Ok(heuristic)
+    }
+
+    /// Chooses the landmarks and calculates their distances
+    pub fn with_selection(graph: &DirectedGraph, selection: &LandmarkSelection) -> Result<LandmarkHeuristic> {
+        match selection {
+            LandmarkSelection::Given(landmarks) => LandmarkHeuristic::new(graph, landmarks),
+            LandmarkSelection::Random(count) => {
+                let landmarks : Vec<usize> = graph.get_vertex_ids().choose_multiple(&mut rand::thread_rng(), *count).cloned().collect();
+                LandmarkHeuristic::new(graph, &landmarks)
+            },
+            LandmarkSelection::Farthest(count) => {
+                let mut heuristic = LandmarkHeuristic::from_tables(Vec::new(), Vec::new(), Vec::new());
+                let reversed = reversed_graph(graph);
+                let ids = graph.get_vertex_ids();
+                // the first landmark is the vertex farthest from the lowest vertex id
+                let mut closest = match ids.first() {
+                    Some(first) => dijkstra_distances(graph, *first)?,
+                    None => return Ok(heuristic),
+                };
+                while heuristic.landmarks.len() < (*count).min(ids.len()) {
+                    // distance from the nearest landmark, where vertexes no landmark reaches
+                    // (Max) are the farthest
+                    let landmark = ids.iter()
+                        .filter(|id| !heuristic.landmarks.contains(id))
+                        .max_by(|a, b| closest[a].cmp(&closest[b]).then(b.cmp(a)))
+                        .cloned()
+                        .unwrap();
+                    debug!("Landmark {} is {} from the other landmarks",landmark,closest[&landmark]);
+                    heuristic.add_landmark(graph, &reversed, landmark)?;
+                    let distances = heuristic.distances.last().unwrap();
+                    if heuristic.landmarks.len() == 1 {
+                        closest = distances.clone();
+                    }
+                    else {
+                        for (id, distance) in closest.iter_mut() {
+                            *distance = (*distance).min(distances[id]);
+                        }
+                    }
+                }
+                Ok(heuristic)
+            },
+        }
+    }
+
+    /// Creates the heuristic from the landmarks and their distance tables (e.g. as read back
+    /// from saved tables)
+    pub fn from_tables(landmarks: Vec<usize>, distances: Vec<BTreeMap<usize,MinMax<i64>>>, backward_distances: Vec<BTreeMap<usize,MinMax<i64>>>) -> LandmarkHeuristic {
+        LandmarkHeuristic { landmarks, distances, backward_distances }
+    }
+
+    // calculates the distances from the landmark on the graph and to it on the reversed graph
+    fn add_landmark(&mut self, graph: &DirectedGraph, reversed: &DirectedGraph, landmark: usize) -> Result<()> {
+        info!("Calculating distances to and from landmark {}",landmark);
+        self.distances.push(dijkstra_distances(graph, landmark)?);
+        self.backward_distances.push(dijkstra_distances(reversed, landmark)?);
+        self.landmarks.push(landmark);
+        Ok(())
+    }
+
+    pub fn landmarks(&self) -> &[usize] {
+        &self.landmarks
+    }
+
+    /// Writes a line for each landmark followed by a line for each vertex with the distance
+    /// from the landmark to the vertex and from the vertex back to the landmark (- if there is
+    /// no path)
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "# landmark distances: {} landmarks", self.landmarks.len())?;
+        let table_value = |distance: &MinMax<i64>| match distance {
+            Value(distance) => distance.to_string(),
+            _ => "-".to_string(),
+        };
+        for (pos, landmark) in self.landmarks.iter().enumerate() {
+            writeln!(writer, "landmark {}", landmark)?;
+            for (vertex, distance) in &self.distances[pos] {
+                let backward = self.backward_distances[pos].get(vertex).unwrap_or(&MinMax::Max);
+                writeln!(writer, "{} {} {}", vertex, table_value(distance), table_value(backward))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the landmark distances written by save.  Lines starting with # are comments
+    /// e.g.
+    ///
+    /// landmark 1
+    /// 1 0 0
+    /// 2 7 -
+    pub fn load<R: Read>(reader: R) -> Result<LandmarkHeuristic> {
+        let reader = BufReader::new(reader);
+        let re_landmark = Regex::new(r"^\s*landmark\s+(?P<landmark>\d+)\s*$").unwrap();
+        let re_distances = Regex::new(r"^\s*(?P<vertex>\d+)\s+(?P<forward>-?\d+|-)\s+(?P<backward>-?\d+|-)\s*$").unwrap();
+
+        let mut landmarks = Vec::<usize>::new();
+        let mut distances = Vec::<BTreeMap<usize,MinMax<i64>>>::new();
+        let mut backward_distances = Vec::<BTreeMap<usize,MinMax<i64>>>::new();
+        for (line_index, line) in reader.lines().enumerate() {
+            let line_count = line_index + 1;
+            let line_data = line?;
+            if line_data.trim().is_empty() || line_data.trim_start().starts_with('#') {
+                continue;
+            }
+            if let Some(caps) = re_landmark.captures(&line_data) {
+                let landmark_match = caps.name("landmark").unwrap();
+                landmarks.push(parse_field::<usize>(landmark_match.as_str(), line_count, landmark_match.start())?);
+                distances.push(BTreeMap::new());
+                backward_distances.push(BTreeMap::new());
+            }
+            else if let Some(caps) = re_distances.captures(&line_data).filter(|_| !landmarks.is_empty()) {
+                let vertex_match = caps.name("vertex").unwrap();
+                let vertex = parse_field::<usize>(vertex_match.as_str(), line_count, vertex_match.start())?;
+                let mut values = Vec::<MinMax<i64>>::new();
+                for name in ["forward", "backward"] {
+                    let field_match = caps.name(name).unwrap();
+                    values.push(match field_match.as_str() {
+                        "-" => MinMax::Max,
+                        text => Value(parse_field::<i64>(text, line_count, field_match.start())?),
+                    });
+                }
+                trace!("Landmark {} vertex {} distances {:?}",landmarks[landmarks.len() - 1],vertex,values);
+                distances.last_mut().unwrap().insert(vertex, values[0]);
+                backward_distances.last_mut().unwrap().insert(vertex, values[1]);
+            }
+            else {
+                error!("Line {} - No landmark or distances found ({})",line_count,line_data);
+                let column = line_data.len() - line_data.trim_start().len();
+                return Err(Error::Parse { line: line_count, column: column + 1, message: "expected landmark or vertex distances".to_string() });
+            }
+        }
+        info!("Read distances of {} landmarks",landmarks.len());
+        Ok(LandmarkHeuristic::from_tables(landmarks, distances, backward_distances))
+    }
+}
+
+impl Heuristic for LandmarkHeuristic {
+    fn estimate(&self, vertex: usize, target: usize) -> i64 {
+        let forward = self.distances.iter()
+            .filter_map(|dist| match (dist.get(&target), dist.get(&vertex)) {
+                (Some(Value(to_target)), Some(Value(to_vertex))) => Some(to_target - to_vertex),
+                _ => None,
+            });
+        let backward = self.backward_distances.iter()
+            .filter_map(|dist| match (dist.get(&vertex), dist.get(&target)) {
+                (Some(Value(from_vertex)), Some(Value(from_target))) => Some(from_vertex - from_target),
+                _ => None,
+            });
+        forward.chain(backward)
+            .max()
+            .unwrap_or(0)
+            .max(0)
+    }
+}
+
+// distances from the start vertex to all the vertexes of the graph
+fn dijkstra_distances(graph: &DirectedGraph, start: usize) -> Result<BTreeMap<usize,MinMax<i64>>> {
+    let mut d = Dijkstra::new(start);
+    for id in graph.get_vertex_ids() {
+        d.initialize_vertex(id);
+    }
+    d.calculate_shortest_paths(graph, start)?;
+    Ok(d.get_shortest_path_distances())
+}
+
+// graph with the same vertexes and each edge reversed, so searching its outgoing edges is the
+// same as searching the incoming edges of the graph
+fn reversed_graph(graph: &DirectedGraph) -> DirectedGraph {
+    let mut reversed = DirectedGraph::new();
+    for id in graph.get_vertex_ids() {
+        reversed.define_vertex(id);
+    }
+    for (_id, e) in graph.edge_iter() {
+        (&mut reversed).add_edge(e.dest(), e.source(), e.weight());
+    }
+    reversed
+}
+
+/// Heuristic using the straight line distance between the coordinates of the vertexes, which
+/// is only a lower bound if no edge is shorter than the distance between its vertexes.
+/// Vertexes without coordinates are estimated as 0
+#[derive(Debug,Clone)]
+pub struct CoordinateHeuristic {
+    coordinates: BTreeMap<usize,(f64,f64)>,
+}
+
+impl CoordinateHeuristic {
+    pub fn new(coordinates: BTreeMap<usize,(f64,f64)>) -> CoordinateHeuristic {
+        CoordinateHeuristic { coordinates }
+    }
+}
+
+impl Heuristic for CoordinateHeuristic {
+    fn estimate(&self, vertex: usize, target: usize) -> i64 {
+        match (self.coordinates.get(&vertex), self.coordinates.get(&target)) {
+            (Some((x1,y1)), Some((x2,y2))) => ((x1 - x2).hypot(y1 - y2)).floor() as i64,
+            _ => 0,
+        }
+    }
+}
+
+
+#[derive(Debug,Clone,PartialOrd,PartialEq)]
+struct SearchInfo {
+    // first entry in the field, so will be used for sorting by min heap by default
+    // (distance from the start + estimate of the remaining distance)
+    estimate: MinMax<i64>,
+    distance: MinMax<i64>,
+}
+
+/// Shortest path between a source and a target, searching the vertexes in order of their
+/// distance from the source plus the heuristic's estimate of the distance to the target
+pub struct AStar<'a> {
+        heuristic: &'a dyn Heuristic,
+        /// Discovered vertexes which have not been settled yet ordered by estimate
+        open_vertex : MinHeap::<SearchInfo>,
+        /// Vertexes whose shortest distance from the source is known
+        settled_vertex : BTreeMap::<usize,MinMax<i64>>,
+        /// preceeding vertex in the path from the source to each discovered vertex
+        predecessor:  BTreeMap<usize,usize>,
+}
+
+
+impl<'a> AStar<'a> {
+
+    pub fn new(heuristic: &'a dyn Heuristic) -> AStar<'a> {
+        AStar {
+            heuristic,
+            open_vertex : MinHeap::<SearchInfo>::new(),
+            settled_vertex : BTreeMap::<usize,MinMax<i64>>::new(),
+            predecessor : BTreeMap::<usize,usize>::new(),
+        }
+    }
+
+    /// Finds the shortest path from source to target.  The distance of the result is Max if
+    /// the target can't be reached from the source
+    /// Returns NegativeEdges if the graph has negative edge weights
+    pub fn calculate_shortest_path(&mut self, graph: &DirectedGraph, source: usize, target: usize) -> Result<ShortestPathInfo> {
+        trace!("Starting A* search from {} to {}",source,target);
+        for vertex in [source, target] {
+            if graph.vertex_index(vertex).is_none() {
+                warn!("Vertex {} is not in the graph",vertex);
+                return Err(Error::UnknownVertex(vertex));
+            }
+        }
+        let negative_edges = graph.get_negative_edge_ids();
+        if !negative_edges.is_empty() {
+            warn!("Graph has {} edges with negative weights",negative_edges.len());
+            return Err(Error::NegativeEdges(negative_edges));
+        }
+
+        self.open_vertex = MinHeap::<SearchInfo>::new();
+        self.settled_vertex.clear();
+        self.predecessor.clear();
+        self.open_vertex.insert(source,SearchInfo { estimate: Value(self.heuristic.estimate(source,target)), distance: Value(0) });
+
+        while let Some((vertex, info)) = self.open_vertex.get_min_entry() {
+            debug!("Settling vertex {} distance {} estimate {}",vertex,info.distance,info.estimate);
+            self.settled_vertex.insert(vertex,info.distance);
+            if vertex == target {
+                break;
+            }
+
+            for e in graph.get_outgoing_edges(vertex)? {
+                if self.settled_vertex.contains_key(&e.dest()) {
+                    continue;
+                }
+                let new_distance = info.distance + Value(e.weight());
+                let new_info = SearchInfo { estimate: new_distance + Value(self.heuristic.estimate(e.dest(),target)), distance: new_distance };
+                match self.open_vertex.peek_id_data(e.dest()) {
+                    Some(cur_info) if new_distance < cur_info.distance => {
+                        trace!("Update distance on {} from {} to {}",e.dest(),cur_info.distance,new_distance);
+                        let index = *self.open_vertex.get_id_index(e.dest()).unwrap();
+                        self.open_vertex.update(index,new_info);
+                        self.predecessor.insert(e.dest(),vertex);
+                    },
+                    Some(_) => (),
+                    None => {
+                        trace!("Discovered {} distance {}",e.dest(),new_distance);
+                        self.open_vertex.insert(e.dest(),new_info);
+                        self.predecessor.insert(e.dest(),vertex);
+                    },
+                }
+            }
+        }
+
+        let distance = self.settled_vertex.get(&target).cloned().unwrap_or(MinMax::Max);
+        info!("A* search settled {} vertexes, distance from {} to {} is {}",self.settled_count(),source,target,distance);
+        if distance == MinMax::Max {
+            return Ok(ShortestPathInfo::unreachable(source, target));
+        }
+        let path = self.find_path(source, target);
+        Ok(ShortestPathInfo {
+            source,
+            dest: target,
+            distance,
+            path_len: path.len(),
+            path,
+            has_negative_cycle: false,
+        })
+    }
+
+    /// Number of vertexes settled by the last search
+    pub fn settled_count(&self) -> usize {
+        self.settled_vertex.len()
+    }
+
+    fn find_path(&self, source: usize, target: usize) -> Vec<usize> {
+        let mut path = vec!(target);
+        let mut current = target;
+        while current != source {
+            current = self.predecessor[&current];
+            path.push(current);
+        }
+        path.reverse();
+        path
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::astar::{AStar, Heuristic, ZeroHeuristic, LandmarkHeuristic, LandmarkSelection, CoordinateHeuristic};
+    use crate::minmax::MinMax::{Value,Max};
+    use crate::error::Error;
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+    // grid of vertexes 1-9 (3 x 3) with edges to the right and down
+	fn setup_grid(mut g :&mut DirectedGraph) -> BTreeMap<usize,(f64,f64)> {
+        let mut coordinates = BTreeMap::new();
+        for row in 0..3 {
+            for col in 0..3 {
+                let v = row * 3 + col + 1;
+                coordinates.insert(v,(col as f64 * 10.0, row as f64 * 10.0));
+                if col < 2 {
+                    g.add_edge(v,v+1,10);
+                }
+                if row < 2 {
+                    g.add_edge(v,v+3,12);
+                }
+            }
+        }
+        coordinates
+	}
+
+    #[test]
+    fn heuristics_agree() {
+        init();
+		let mut g = DirectedGraph::new();
+        let coordinates = setup_grid(&mut g);
+        let zero = ZeroHeuristic;
+        let landmark = LandmarkHeuristic::new(&g,&[1]).unwrap();
+        let coordinate = CoordinateHeuristic::new(coordinates);
+
+        let mut zero_search = AStar::new(&zero);
+        let result = zero_search.calculate_shortest_path(&g,1,9).unwrap();
+        assert_eq!(result.distance,Value(44));
+        assert_eq!((result.path.first(),result.path.last(),result.path_len),(Some(&1),Some(&9),5));
+        let zero_settled = zero_search.settled_count();
+
+        let mut coordinate_search = AStar::new(&coordinate);
+        let result = coordinate_search.calculate_shortest_path(&g,1,9).unwrap();
+        assert_eq!(result.distance,Value(44));
+        assert!(coordinate_search.settled_count() <= zero_settled);
+
+        let mut landmark_search = AStar::new(&landmark);
+        let result = landmark_search.calculate_shortest_path(&g,2,9).unwrap();
+        assert_eq!(result.distance,Value(34));
+        assert!(landmark_search.settled_count() <= zero_settled);
+    }
+
+    #[test]
+    fn unreachable() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+        let zero = ZeroHeuristic;
+        let mut search = AStar::new(&zero);
+        let result = search.calculate_shortest_path(&g,9,1).unwrap();
+        assert_eq!(result.distance,Max);
+        assert_eq!(result.path,vec!(1));
+        assert!(search.calculate_shortest_path(&g,1,10).is_err());
+    }
+
+    #[test]
+    fn negative_edges() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+		let edge = (&mut g).add_edge(5,9,-30).unwrap();
+        let zero = ZeroHeuristic;
+        let mut search = AStar::new(&zero);
+        assert!(matches!(search.calculate_shortest_path(&g,1,9), Err(Error::NegativeEdges(ids)) if ids == vec!(edge)));
+    }
+
+    #[test]
+    fn landmark_selection() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+        // 9 is farthest from 1, and nothing is reachable from 9 so the next is the lowest id
+        let farthest = LandmarkHeuristic::with_selection(&g, &LandmarkSelection::Farthest(2)).unwrap();
+        assert_eq!(farthest.landmarks(),&[9,1]);
+        // exact for paths to a landmark using the distances back to it
+        assert_eq!(farthest.estimate(1,9),44);
+        assert_eq!(farthest.estimate(2,9),34);
+        assert_eq!(farthest.estimate(9,1),0);
+
+        let random = LandmarkHeuristic::with_selection(&g, &LandmarkSelection::Random(3)).unwrap();
+        let mut landmarks = random.landmarks().to_vec();
+        landmarks.sort_unstable();
+        landmarks.dedup();
+        assert_eq!(landmarks.len(),3);
+        let given = LandmarkHeuristic::with_selection(&g, &LandmarkSelection::Given(vec!(5))).unwrap();
+        assert_eq!(given.landmarks(),&[5]);
+    }
+
+    #[test]
+    fn save_and_load_landmarks() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+        let landmark = LandmarkHeuristic::new(&g,&[5,3]).unwrap();
+        let mut saved = Vec::<u8>::new();
+        landmark.save(&mut saved).unwrap();
+        let loaded = LandmarkHeuristic::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.landmarks(),&[5,3]);
+        for vertex in g.get_vertex_ids() {
+            for target in g.get_vertex_ids() {
+                assert_eq!(loaded.estimate(vertex,target),landmark.estimate(vertex,target));
+            }
+        }
+        let mut search = AStar::new(&loaded);
+        assert_eq!(search.calculate_shortest_path(&g,1,9).unwrap().distance,Value(44));
+    }
+
+}
@@ -1,110 +1,166 @@
 extern crate two_d_array;
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap, VecDeque};
 use two_d_array::TwoDArray;
-use std::ops::{Add,Sub};
 
 use crate::dirgraph::DirectedGraph;
 
 use log::{ info, error, debug, /*warn,*/ trace };
 
-use std::fmt;
 use crate::minmax::MinMax;
 use crate::minmax::MinMax::{Value,NA};
 use crate::ShortestPathInfo;
+use crate::cycle::Cycle;
+use crate::error::{Error, Result};
 
 
 #[derive(Debug)]
 pub struct Bellman {
-        /// Two dimensional array containing the length of the shortest path to each vertex for
-        /// each iteration (size is )
-        distances:  TwoDArray<MinMax<i64>>,
+        /// Length of the shortest path to each vertex found as of the previous iteration
+        /// (indexed by the dense vertex index)
+        previous:  Vec<MinMax<i64>>,
+        /// Length of the shortest path to each vertex being calculated in the current iteration
+        current:  Vec<MinMax<i64>>,
+        /// Optional two dimensional array containing the length of the shortest path to each
+        /// vertex (column, by dense index) for each iteration (row) -- only kept when record
+        /// history is enabled as it is O(V^2) in size
+        history:  Option<TwoDArray<MinMax<i64>>>,
+        record_history: bool,
+        /// each iteration finds the shortest walks of exactly (rather than at most) that many
+        /// edges, running every iteration -- used with the history for Karp's algorithm
+        exact_walks: bool,
         /// For each vertex the precessor contains the preceeding vertex in the path.  The vertexes
         /// of shortest path can be found by traversing the precessor vertexes back to the source
         predecessor:  BTreeMap<usize,MinMax<usize>>,
-        // number of vertexes is set to 1 more than the actually number of vertex to simplify having vertex numbers starting at one without a
-        // mapping.  Vertex 0 is unused
+        /// For each vertex with a predecessor, the id and weight of the edge from the predecessor
+        predecessor_edge:  BTreeMap<usize,(usize,i64)>,
+        /// vertex ids of the graph in dense index order and the reverse mapping of vertex id to
+        /// index, so that results can be reported by vertex id
+        vertex_ids: Vec<usize>,
+        vertex_index: BTreeMap<usize,usize>,
+        // number of vertexes in the graph
         num_vertex: usize,
-        // number of iterations -- genearlly set to the same as the number of vertexes to allow
-        // detection of negative cycles.  (Note that this also supports vertex 0 even if the first
-        // vertex id is 1 as not to have to map from vertex ids to indexes.) 
+        // number of iterations -- set to one more than the number of vertexes so that
+        // the iteration after the longest possible path (num_vertex-1 edges) is run to allow
+        // detection of negative cycles.
         iterations: usize,
         last_iteration: usize,
         starting_vertex: usize,
+        /// Initial distance of each of the sources of the last calculation
+        source_offsets: BTreeMap<usize,i64>,
         found_negative_cycle : bool,
+        /// Negative cycle found by the last calculation (if any)
+        negative_cycle: Option<Cycle>,
 }
 
 
 impl Bellman {
 
-    pub fn new(num_vertex: usize ) -> Bellman {
-        let width = num_vertex+1;
-        let height = num_vertex+1;
-        let mut preceeding = BTreeMap::<usize,MinMax<usize>>::new();
-
-
-        // initialize the preceeding vertex to Max indicating no path yet
-        for id in 0..width {
-            preceeding.insert(id,NA);
-        }
+    /// Creates a Bellman instance -- the distances are sized from the graph by each
+    /// calculation
+    pub fn new() -> Bellman {
 
         Bellman { 
-            distances:  TwoDArray::<MinMax<i64>>::new(width,height,MinMax::Max),
-            predecessor:  preceeding,
-            num_vertex: width,
-            iterations: height,
+            previous:  Vec::<MinMax<i64>>::new(),
+            current:  Vec::<MinMax<i64>>::new(),
+            history:  None,
+            record_history: false,
+            exact_walks: false,
+            predecessor:  BTreeMap::<usize,MinMax<usize>>::new(),
+            predecessor_edge:  BTreeMap::<usize,(usize,i64)>::new(),
+            vertex_ids: Vec::<usize>::new(),
+            vertex_index: BTreeMap::<usize,usize>::new(),
+            num_vertex: 0,
+            iterations: 0,
             last_iteration: 0,
             starting_vertex: 0,
+            source_offsets: BTreeMap::<usize,i64>::new(),
             found_negative_cycle: false,
+            negative_cycle: None,
         }
 
     }
 
+    /// Creates a Bellman instance that also records the distances calculated in every
+    /// iteration (see history)
+    pub fn with_history() -> Bellman {
+        let mut bellman = Bellman::new();
+        bellman.record_history = true;
+        bellman
+    }
+
+    /// Creates a Bellman instance that records, for every iteration k, the shortest walk of
+    /// exactly k edges to each vertex (see history).  Walks may repeat vertexes, so all of
+    /// the iterations are run and negative cycles are not reported
+    pub fn with_walk_history() -> Bellman {
+        let mut bellman = Bellman::with_history();
+        bellman.exact_walks = true;
+        bellman
+    }
+
+    /// Returns the table of distances for each iteration (row) and vertex (column, by dense
+    /// vertex index) if record history was enabled
+    pub fn history(&self) -> Option<&TwoDArray<MinMax<i64>>> {
+        self.history.as_ref()
+    }
+
+    /// Returns the index of the last iteration executed by the last calculation
+    pub fn last_iteration(&self) -> usize {
+        self.last_iteration
+    }
+
     /// Find the shortest path from a starting vertex to all other vertexes in the graph
-    pub fn calculate_shortest_paths(&mut self, graph: &DirectedGraph, starting_vertex: usize) {
-        info!("Starting shortest path with {}",starting_vertex);
-        self.found_negative_cycle = false;
+    pub fn calculate_shortest_paths(&mut self, graph: &DirectedGraph, starting_vertex: usize) -> Result<()> {
+        self.calculate_shortest_paths_multi(graph, &[(starting_vertex,0)])
+    }
 
-        // initialite the first iteration with the distance from the starting
-        // vertex to itself as 0 -- all other items will be left at none
-        self.distances.set(starting_vertex,0,MinMax::Value(0));
-        self.starting_vertex = starting_vertex;
+    /// Find the shortest path from the nearest of several sources to all other vertexes, each
+    /// source given with an initial offset added to the distance of paths starting from it.
+    /// The source of each path is the one it originates from
+    pub fn calculate_shortest_paths_multi(&mut self, graph: &DirectedGraph, sources: &[(usize,i64)]) -> Result<()> {
+        info!("Starting shortest path with {:?}",sources);
+
+        // initialite the first iteration with the distance from each source to itself as its
+        // offset -- all other items will be left at none
+        self.prepare(graph, sources)?;
+        if let Some(history) = self.history.as_mut() {
+            for (index, dist) in self.previous.iter().enumerate() {
+                history.set(index,0,*dist);
+            }
+        }
 
 
-        // vertex ids start at 1.
         for iteration in 1..self.iterations {
             info!("Iteration {}",iteration);
             let mut changes_during_iteration = false;
-            for (id,_v) in graph.vertex_iter() {
-                let edges = graph.get_incoming_edges(*id);
+            let mut last_changed = None;
+            for (id,v) in graph.vertex_iter() {
+                let edges = graph.get_incoming_edges(*id)?;
 
-                let mut incoming_distances = Vec::<(MinMax<i64>,usize)>::new();
+                let mut incoming_distances = Vec::<(MinMax<i64>,usize,usize,i64)>::new();
                 for e in edges {
-                    if let Ok(dist) = self.distances.get(e.source(),iteration-1) {
-                        if let Value(edge_distance) = dist {
-                            if dist < MinMax::<i64>::Max {
-                                //push a tuple with the new weight as primary element and source vertex
-                                //as 2nd
-let this_distance = MinMax::Value(edge_distance + e.weight());
-                                let this_entry = (this_distance.clone(),e.source());
-                                debug!("Adding {} from {} {:?}",this_distance, e.source(),this_entry);
-                                incoming_distances.push(this_entry);
-                            }
-                        }
+                    if let Value(edge_distance) = self.previous[e.source_index()] {
+                        //push a tuple with the new weight as primary element and source vertex
+                        //as 2nd (followed by the edge id and weight)
+                        let this_distance = MinMax::Value(edge_distance + e.weight());
+                        let this_entry = (this_distance,e.source(),e.id(),e.weight());
+                        debug!("Adding {} from {} {:?}",this_distance, e.source(),this_entry);
+                        incoming_distances.push(this_entry);
                     }
                 }
 
-                // start with the values from the last iteration
-                let last = self.distances.get(*id,iteration-1).unwrap();
+                // start with the values from the last iteration (unless only walks of exactly
+                // this many edges are wanted)
+                let last = self.previous[v.index()];
                 debug!("Vertex {} last iteration value was {}",id,last);
 
-                let mut new = last;
+                let mut new = if self.exact_walks { MinMax::Max } else { last };
                 // mark the source as the current node (indicating we are taking the last value)
-                let mut source = id.clone();
+                let mut source = *id;
 
-                if incoming_distances.len() > 0 {
+                if !incoming_distances.is_empty() {
                     // find the min of the incoming distances (which are in a tuple)
                     debug!("Incoming option {:?}",incoming_distances);
-                    let (incoming_min, incoming_source) = incoming_distances.iter().min().unwrap();
+                    let (incoming_min, incoming_source, edge_id, edge_weight) = incoming_distances.iter().min().unwrap();
                     
 
                     //check to see if the incoming value is less, if so update the fields from the
@@ -113,7 +169,9 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
                         new = *incoming_min;
                         source = *incoming_source;
                         changes_during_iteration = true;
-                        self.predecessor.insert(*id, Value(source.clone()));
+                        last_changed = Some(*id);
+                        self.predecessor.insert(*id, Value(source));
+                        self.predecessor_edge.insert(*id, (*edge_id, *edge_weight));
 
                     }
                 }
@@ -121,50 +179,187 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
                     trace!("Vertex {} - no incoming edges",id);
                 }
                 // set the new value
-                self.distances.set(*id,iteration,new);
+                self.current[v.index()] = new;
                 debug!("Vertex {} iter: {} last {:?} cur via {} -> {:?} new {:?}",id,iteration,last,source,last,new);
 
             }
+            // the current iteration becomes the previous one for the next round
+            std::mem::swap(&mut self.previous, &mut self.current);
+            if let Some(history) = self.history.as_mut() {
+                for (index, dist) in self.previous.iter().enumerate() {
+                    history.set(index,iteration,*dist);
+                }
+            }
             self.last_iteration += 1;
-            if !changes_during_iteration {
+            if self.exact_walks {
+                debug!("Walks of {} edges found", iteration);
+            }
+            else if !changes_during_iteration {
                 info!("No changes during iteration {} ... finishing", iteration);
                 break;
             }
             else if iteration == self.iterations-1 {
                 info!("changes deteted in {} iteration --- Graph has a negative cycle", iteration);
                 self.found_negative_cycle = true;
+                self.negative_cycle = Cycle::from_predecessors(&self.predecessor, &self.predecessor_edge, &last_changed.into_iter().collect::<Vec<usize>>());
 
             }
         }
 
-        let mut count = 0;
-        let header : String = (0..self.num_vertex).map(|index| format!("{:>4} ",index) ).collect();
-        trace!("{:13}{}","Vertex",header);
-            
+        if let Some(history) = self.history.as_ref() {
+            let header : String = self.vertex_ids.iter().map(|id| format!("{:>4} ",id) ).collect();
+            trace!("{:13}{}","Vertex",header);
 
-        for row in self.distances.get_row_iter() {
-            let min = row.iter().min().unwrap();
-            let row_format : String = row.iter().map(|val| format!("{:>4} ",val) ).collect();
-            trace!("Iter {:2} :    {}  Min: {}", count,row_format, min);
-            count += 1
+            for (count, row) in history.get_row_iter().enumerate().take(self.last_iteration+1) {
+                let min = row.iter().min().unwrap();
+                let row_format : String = row.iter().map(|val| format!("{:>4} ",val) ).collect();
+                trace!("Iter {:2} :    {}  Min: {}", count,row_format, min);
+            }
+        }
+        Ok(())
+
+    }
+
+    /// Find the shortest path from a starting vertex to all other vertexes in the graph using
+    /// a queue based variant (SPFA).  Each round only relaxes the outgoing edges of the vertexes
+    /// whose distance changed in the previous round, and a negative cycle is detected when a
+    /// vertex has been queued more times than there are vertexes in the graph.  Iteration
+    /// history is not recorded in this mode.
+    pub fn calculate_shortest_paths_queue(&mut self, graph: &DirectedGraph, starting_vertex: usize) -> Result<()> {
+        self.calculate_shortest_paths_queue_multi(graph, &[(starting_vertex,0)])
+    }
+
+    /// Queue based variant of calculate_shortest_paths_multi
+    pub fn calculate_shortest_paths_queue_multi(&mut self, graph: &DirectedGraph, sources: &[(usize,i64)]) -> Result<()> {
+        info!("Starting queue based shortest path with {:?}",sources);
+
+        self.prepare(graph, sources)?;
 
+        // number of times each vertex has been added to the queue, and whether it is currently
+        // waiting in the queue for the next round (both by dense index)
+        let mut enqueue_count = vec![0_usize; self.num_vertex];
+        let mut queued = vec![false; self.num_vertex];
+
+        let mut queue = VecDeque::<usize>::new();
+        for source in self.source_offsets.keys() {
+            let index = self.vertex_index[source];
+            queue.push_back(*source);
+            queued[index] = true;
+            enqueue_count[index] = 1;
         }
 
+        while !queue.is_empty() && !self.found_negative_cycle {
+            self.last_iteration += 1;
+            // the vertexes currently in the queue are the ones changed in the last round
+            let round_size = queue.len();
+            info!("Round {} processing {} vertexes",self.last_iteration,round_size);
+
+            for _ in 0..round_size {
+                let id = queue.pop_front().unwrap();
+                let index = self.vertex_index[&id];
+                queued[index] = false;
+                let cur_distance = match self.previous[index] {
+                    Value(distance) => distance,
+                    _ => continue,
+                };
+
+                for e in graph.get_outgoing_edges(id)? {
+                    let dest_index = e.dest_index();
+                    let new_distance = MinMax::Value(cur_distance + e.weight());
+                    if new_distance < self.previous[dest_index] {
+                        debug!("Vertex {} round: {} updated from {} to {} via {}",e.dest(),self.last_iteration,self.previous[dest_index],new_distance,id);
+                        self.previous[dest_index] = new_distance;
+                        self.predecessor.insert(e.dest(), Value(id));
+                        self.predecessor_edge.insert(e.dest(), (e.id(), e.weight()));
+
+                        if !queued[dest_index] {
+                            queued[dest_index] = true;
+                            enqueue_count[dest_index] += 1;
+                            queue.push_back(e.dest());
+                            if enqueue_count[dest_index] > self.num_vertex {
+                                info!("Vertex {} queued {} times --- Graph has a negative cycle", e.dest(), enqueue_count[dest_index]);
+                                self.found_negative_cycle = true;
+                                self.negative_cycle = Cycle::from_predecessors(&self.predecessor, &self.predecessor_edge, &[e.dest()]);
+                                break;
+                            }
+                        }
+                    }
+                }
+                if self.found_negative_cycle {
+                    break;
+                }
+            }
+        }
+        info!("Queue based shortest path complete after {} rounds",self.last_iteration);
+        Ok(())
+
     }
 
     pub fn has_negative_cycle(&self) -> bool {
         self.found_negative_cycle
     }
+
+    /// Returns the negative cycle found by the last calculation, as recovered from the
+    /// predecessors of the vertexes
+    pub fn negative_cycle(&self) -> Option<Cycle> {
+        self.negative_cycle.clone()
+    }
+
+    // sets up the vertex mapping and clears the results of any previous calculation, with
+    // the initial distance of each source set to its offset (the lowest if repeated)
+    fn prepare(&mut self, graph: &DirectedGraph, sources: &[(usize,i64)]) -> Result<()> {
+        let mut source_offsets = BTreeMap::<usize,i64>::new();
+        for (source, offset) in sources {
+            if graph.vertex_index(*source).is_none() {
+                error!("Starting vertex {} is not in the graph",source);
+                return Err(Error::UnknownVertex(*source));
+            }
+            let entry = source_offsets.entry(*source).or_insert(*offset);
+            *entry = (*entry).min(*offset);
+        }
+
+        self.num_vertex = graph.vertex_count();
+        self.iterations = self.num_vertex+1;
+        self.vertex_ids = graph.vertex_ids_by_index().to_vec();
+        self.vertex_index = self.vertex_ids.iter().enumerate().map(|(index, id)| (*id, index)).collect();
+
+        self.previous = vec![MinMax::Max; self.num_vertex];
+        self.current = vec![MinMax::Max; self.num_vertex];
+        if self.record_history {
+            self.history = Some(TwoDArray::<MinMax<i64>>::new(self.num_vertex,self.iterations,MinMax::Max));
+        }
+
+        // initialize the preceeding vertex to NA indicating no path yet
+        self.predecessor = self.vertex_ids.iter().map(|id| (*id, NA)).collect();
+        self.predecessor_edge.clear();
+
+        for (source, offset) in source_offsets.iter() {
+            self.previous[self.vertex_index[source]] = MinMax::Value(*offset);
+        }
+        self.starting_vertex = sources.first().map_or(0, |(source, _offset)| *source);
+        self.source_offsets = source_offsets;
+        self.last_iteration = 0;
+        self.found_negative_cycle = false;
+        self.negative_cycle = None;
+        Ok(())
+    }
+
+    // paths are followed back until reaching a source whose distance is still its offset
+    // (rather than a path from another source), or any source if there is a negative cycle
+    fn is_path_start(&self, vertex: usize) -> bool {
+        match self.source_offsets.get(&vertex) {
+            Some(offset) => self.found_negative_cycle || self.get_shortest_path_distance(vertex) >= Value(*offset),
+            None => false,
+        }
+    }
    
     /// Returns the shortest disntance calcuated from the starting vertex previously defined
     /// to the dest_vertex provided. 
-    /// Returns NA if the dest_vertex is out of rant
+    /// Returns NA if the dest_vertex is not in the graph
     pub fn get_shortest_path_distance (&self,dest_vertex: usize ) -> MinMax<i64> {
-        if dest_vertex < self.num_vertex {
-            self.distances.get(dest_vertex, self.last_iteration).unwrap()
-        }
-        else {
-            MinMax::NA
+        match self.vertex_index.get(&dest_vertex) {
+            Some(index) => self.previous[*index],
+            None => MinMax::NA,
         }
     }
         
@@ -172,14 +367,10 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
     /// to each of the rest of the vertexes 
     pub fn get_shortest_path_distances(&self) -> BTreeMap<usize,MinMax<i64>> {
 
-        let mut result_list = BTreeMap::<usize,MinMax<i64>>::new();
-        let mut index = 0;
-        for result in self.distances.get_row(self.last_iteration) {
-            result_list.insert(index,result.clone());
-            index += 1;
-        }
-
-        result_list
+        self.vertex_ids.iter()
+            .zip(self.previous.iter())
+            .map(|(id, result)| (*id, *result))
+            .collect()
 
     }
 
@@ -194,7 +385,7 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
         
         // unless this vertex doesn't have a predecessor (indicating no path from starting vertex)
         // add it to the end of the path
-        if self.predecessor[&dest_vertex] != NA || dest_vertex == self.starting_vertex {
+        if self.predecessor[&dest_vertex] != NA || self.source_offsets.contains_key(&dest_vertex) {
 
             let mut current_vertex = dest_vertex;
             let mut done = false;
@@ -208,7 +399,7 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
                 else {
                     error!("Unexpected Value");
                 }
-                if predecessor_count > self.num_vertex || current_vertex == self.starting_vertex {
+                if predecessor_count > self.num_vertex || self.is_path_start(current_vertex) {
                     done = true;
                 }
 
@@ -222,40 +413,25 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
 
     pub fn get_shortest_paths(&self) -> BTreeMap<usize, ShortestPathInfo> {
         let mut result = BTreeMap::<usize,ShortestPathInfo>::new();
-        /*
-        for v in vertex_list {
-            let path = self.find_path(v);
-            let has_cycle = path.len() > self.num_vertex;
-            result.insert(v,(path,has_cycle));
-            
-        }
-        result
-        */
 
-        let mut index = 0;
+        for (id, distance) in self.vertex_ids.iter().zip(self.previous.iter()) {
 
-        for distance in self.distances.get_row(self.last_iteration) {
-            // TODO -- hack!!!!
-            if index == 0 {
-                index += 1;
-                continue;
-            }
-
-            let path = self.find_path(index);
+            let path = self.find_path(*id);
             let path_len = path.len();
             let has_negative_cycle = path.len() > self.num_vertex;
+            // with several sources, the path starts at the one nearest to the vertex
+            let source = if self.source_offsets.contains_key(&path[0]) { path[0] } else { self.starting_vertex };
 
             let entry = ShortestPathInfo {
-                source: self.starting_vertex,
-                dest: index,
+                source,
+                dest: *id,
                 distance: *distance,
                 path,
                 path_len,
                 has_negative_cycle,
             };
             trace!("info {:?}",entry);
-            result.insert(index,entry);
-            index += 1;
+            result.insert(*id,entry);
         }
         result
 
@@ -263,3 +439,130 @@ let this_distance = MinMax::Value(edge_distance + e.weight());
 
 
 }
+
+impl Default for Bellman {
+    fn default() -> Self {
+        Bellman::new()
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::bellman::Bellman;
+    use crate::minmax::MinMax::{Value,Max};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+	fn setup_basic(mut g :&mut DirectedGraph) { 
+		assert_eq!(g.add_edge(1,2,12),Some(1));
+		assert_eq!(g.add_edge(1,3,-13),Some(2));
+		assert_eq!(g.add_edge(2,3,23),Some(3));
+		assert_eq!(g.add_edge(2,4,-24),Some(4));
+		assert_eq!(g.add_edge(3,4,34),Some(5));
+		assert_eq!(g.add_edge(4,5,-45),Some(6));
+		g.define_vertex(6);
+	} 
+
+    #[test]
+    fn basic() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths(&g,1).unwrap();
+        assert!(!b.has_negative_cycle());
+        assert_eq!(b.get_shortest_path_distance(4),Value(-12));
+        assert_eq!(b.get_shortest_path_distance(5),Value(-57));
+        assert_eq!(b.get_shortest_path_distance(6),Max);
+        assert_eq!(b.get_shortest_paths()[&5].path,vec!(1,2,4,5));
+        assert!(b.history().is_none());
+        assert!(b.negative_cycle().is_none());
+    }
+
+    #[test]
+    fn history_matches() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths(&g,1).unwrap();
+        let mut h = Bellman::with_history();
+        h.calculate_shortest_paths(&g,1).unwrap();
+        assert_eq!(b.get_shortest_path_distances(),h.get_shortest_path_distances());
+        assert_eq!(b.last_iteration(),h.last_iteration());
+
+        let history = h.history().unwrap();
+        let index = |id| g.vertex_index(id).unwrap();
+        assert_eq!(history.get(index(1),0).unwrap(),Value(0));
+        assert_eq!(history.get(index(2),0).unwrap(),Max);
+        assert_eq!(history.get(index(2),1).unwrap(),Value(12));
+        assert_eq!(history.get(index(5),h.last_iteration()).unwrap(),Value(-57));
+    }
+
+    #[test]
+    fn negative_cycle() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+		assert_eq!((&mut g).add_edge(5,1,10),Some(7));
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths(&g,1).unwrap();
+        assert!(b.has_negative_cycle());
+
+        let cycle = b.negative_cycle().unwrap();
+        assert_eq!(cycle.vertexes,vec!(1,2,4,5));
+        assert_eq!(cycle.edges,vec!(1,4,6,7));
+        assert_eq!(cycle.weight,-47);
+
+        let mut q = Bellman::new();
+        q.calculate_shortest_paths_queue(&g,1).unwrap();
+        assert!(q.has_negative_cycle());
+        assert_eq!(q.negative_cycle(),Some(cycle));
+    }
+
+    #[test]
+    fn queue_matches() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+		assert_eq!((&mut g).add_edge(5,6,2),Some(7));
+		assert_eq!((&mut g).add_edge(1,6,-50),Some(8));
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths(&g,1).unwrap();
+        let mut q = Bellman::new();
+        q.calculate_shortest_paths_queue(&g,1).unwrap();
+        assert!(!q.has_negative_cycle());
+        assert_eq!(b.get_shortest_path_distances(),q.get_shortest_path_distances());
+        assert_eq!(q.get_shortest_path_distance(6),Value(-55));
+        assert_eq!(q.get_shortest_paths()[&6].path,vec!(1,2,4,5,6));
+    }
+
+    #[test]
+    fn multi_source() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+        let sources = [(1,0),(4,-20),(3,0)];
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths_multi(&g,&sources).unwrap();
+        let paths = b.get_shortest_paths();
+        assert_eq!((paths[&2].source,paths[&2].distance),(1,Value(12)));
+        assert_eq!((paths[&3].source,paths[&3].path.clone()),(1,vec!(1,3)));
+        assert_eq!((paths[&4].source,paths[&4].distance),(4,Value(-20)));
+        assert_eq!((paths[&5].source,paths[&5].distance),(4,Value(-65)));
+        assert_eq!(paths[&5].path,vec!(4,5));
+
+        let mut q = Bellman::new();
+        q.calculate_shortest_paths_queue_multi(&g,&sources).unwrap();
+        assert_eq!(b.get_shortest_path_distances(),q.get_shortest_path_distances());
+        assert!(b.calculate_shortest_paths_multi(&g,&[(9,0)]).is_err());
+    }
+
+}
@@ -0,0 +1,253 @@
+extern crate minheap;
+use std::collections::{BTreeMap, BTreeSet};
+use minheap::MinHeap;
+
+use crate::dirgraph::DirectedGraph;
+
+use log::{ info, debug, warn, trace };
+use crate::minmax::{MinMax,MinMax::Value};
+use crate::shortpathinfo::ShortestPathInfo;
+use crate::error::{Error, Result};
+
+// State of the search in one direction -- forward from the source on the outgoing edges or
+// backward from the target on the incoming edges
+struct SearchSide {
+    forward: bool,
+    /// Discovered vertexes which have not been settled yet ordered by distance
+    open_vertex : MinHeap::<MinMax<i64>>,
+    /// best distance found so far for each discovered vertex (final once settled)
+    distance: BTreeMap<usize,MinMax<i64>>,
+    settled: BTreeSet<usize>,
+    /// next vertex towards the start of this side's search
+    predecessor: BTreeMap<usize,usize>,
+    /// distance of the last vertex settled -- all vertexes closer than this are settled
+    radius: MinMax<i64>,
+    exhausted: bool,
+}
+
+impl SearchSide {
+
+    fn new(start: usize, forward: bool) -> SearchSide {
+        let mut open_vertex = MinHeap::<MinMax<i64>>::new();
+        open_vertex.insert(start,Value(0));
+        SearchSide {
+            forward,
+            open_vertex,
+            distance: BTreeMap::from([(start,Value(0))]),
+            settled: BTreeSet::<usize>::new(),
+            predecessor: BTreeMap::<usize,usize>::new(),
+            radius: Value(0),
+            exhausted: false,
+        }
+    }
+
+    // settles the closest open vertex and relaxes its edges, updating the best path found
+    // (distance and meeting vertex) when reaching a vertex already found by the other side
+    fn step(&mut self, other: &SearchSide, graph: &DirectedGraph, best: &mut (MinMax<i64>, Option<usize>)) -> Result<()> {
+        let (vertex, distance) = match self.open_vertex.get_min_entry() {
+            Some(entry) => entry,
+            None => {
+                self.exhausted = true;
+                return Ok(());
+            }
+        };
+        trace!("{} search settling {} distance {}", if self.forward { "Forward" } else { "Backward" }, vertex, distance);
+        self.settled.insert(vertex);
+        self.radius = distance;
+
+        let edges = if self.forward { graph.get_outgoing_edges(vertex)? } else { graph.get_incoming_edges(vertex)? };
+        for e in edges {
+            let next = if self.forward { e.dest() } else { e.source() };
+            if self.settled.contains(&next) {
+                continue;
+            }
+            let new_distance = distance + Value(e.weight());
+            match self.distance.get(&next) {
+                Some(cur_distance) if new_distance >= *cur_distance => (),
+                Some(_) => {
+                    let index = *self.open_vertex.get_id_index(next).unwrap();
+                    self.open_vertex.update(index,new_distance);
+                    self.distance.insert(next,new_distance);
+                    self.predecessor.insert(next,vertex);
+                },
+                None => {
+                    self.open_vertex.insert(next,new_distance);
+                    self.distance.insert(next,new_distance);
+                    self.predecessor.insert(next,vertex);
+                },
+            }
+            if let Some(other_distance) = other.distance.get(&next) {
+                let total = self.distance[&next] + *other_distance;
+                if total < best.0 {
+                    debug!("Paths meet at {} with distance {}",next,total);
+                    *best = (total, Some(next));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // path from the start of this side's search to vertex (in the order found by this side)
+    fn path_to(&self, vertex: usize) -> Vec<usize> {
+        let mut path = vec!(vertex);
+        let mut current = vertex;
+        while let Some(prev) = self.predecessor.get(&current) {
+            path.push(*prev);
+            current = *prev;
+        }
+        path
+    }
+}
+
+/// Point to point shortest path using Dijkstra searches forward from the source (on outgoing
+/// edges) and backward from the target (on incoming edges) until the searches meet
+pub struct BidirectionalDijkstra {
+    settled_count: usize,
+    /// skips checking the graph for negative edge weights (which give incorrect results)
+    allow_negative: bool,
+}
+
+impl BidirectionalDijkstra {
+
+    pub fn new() -> BidirectionalDijkstra {
+        BidirectionalDijkstra { settled_count: 0, allow_negative: false }
+    }
+
+    /// Creates a search that doesn't reject graphs with negative edge weights
+    pub fn with_allow_negative() -> BidirectionalDijkstra {
+        BidirectionalDijkstra { settled_count: 0, allow_negative: true }
+    }
+
+    /// Finds the shortest path from source to target.  The distance of the result is Max if
+    /// the target can't be reached from the source
+    /// Returns NegativeEdges if the graph has negative edge weights (unless allowed)
+    pub fn calculate_shortest_path(&mut self, graph: &DirectedGraph, source: usize, target: usize) -> Result<ShortestPathInfo> {
+        for vertex in [source, target] {
+            if graph.vertex_index(vertex).is_none() {
+                warn!("Vertex {} is not in the graph",vertex);
+                return Err(Error::UnknownVertex(vertex));
+            }
+        }
+        if !self.allow_negative {
+            let negative_edges = graph.get_negative_edge_ids();
+            if !negative_edges.is_empty() {
+                warn!("Graph has {} edges with negative weights",negative_edges.len());
+                return Err(Error::NegativeEdges(negative_edges));
+            }
+        }
+
+        let mut forward = SearchSide::new(source, true);
+        let mut backward = SearchSide::new(target, false);
+        let mut best = if source == target { (Value(0), Some(source)) } else { (MinMax::Max, None) };
+
+        // alternate between the two directions, stopping once the sum of the distances settled
+        // in each direction reaches the best path found, since any shorter path would have to
+        // go through a vertex that isn't settled in either direction
+        let mut forward_turn = true;
+        while !(forward.exhausted && backward.exhausted) && forward.radius + backward.radius < best.0 {
+            if (forward_turn && !forward.exhausted) || backward.exhausted {
+                forward.step(&backward, graph, &mut best)?;
+            }
+            else {
+                backward.step(&forward, graph, &mut best)?;
+            }
+            forward_turn = !forward_turn;
+        }
+        self.settled_count = forward.settled.len() + backward.settled.len();
+
+        info!("Bidirectional search settled {} vertexes, distance from {} to {} is {}",self.settled_count,source,target,best.0);
+        let meet = match best.1 {
+            Some(meet) => meet,
+            None => return Ok(ShortestPathInfo::unreachable(source, target)),
+        };
+        let mut path = forward.path_to(meet);
+        path.reverse();
+        path.extend(backward.path_to(meet).into_iter().skip(1));
+        Ok(ShortestPathInfo {
+            source,
+            dest: target,
+            distance: best.0,
+            path_len: path.len(),
+            path,
+            has_negative_cycle: false,
+        })
+    }
+
+    /// Number of vertexes settled (in both directions) by the last search
+    pub fn settled_count(&self) -> usize {
+        self.settled_count
+    }
+}
+
+impl Default for BidirectionalDijkstra {
+    fn default() -> Self {
+        BidirectionalDijkstra::new()
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::bidirectional::BidirectionalDijkstra;
+    use crate::dijkstra::Dijkstra;
+    use crate::minmax::MinMax::{Value,Max};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,7),Some(1));
+		assert_eq!(g.add_edge(1,3,9),Some(2));
+		assert_eq!(g.add_edge(1,6,14),Some(3));
+		assert_eq!(g.add_edge(2,3,10),Some(4));
+		assert_eq!(g.add_edge(2,4,15),Some(5));
+		assert_eq!(g.add_edge(3,4,11),Some(6));
+		assert_eq!(g.add_edge(3,6,2),Some(7));
+		assert_eq!(g.add_edge(4,5,6),Some(8));
+		assert_eq!(g.add_edge(6,5,9),Some(9));
+		g.define_vertex(7);
+	}
+
+    #[test]
+    fn matches_dijkstra() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        for source in g.get_vertex_ids() {
+            let mut d = Dijkstra::new(source);
+            for id in g.get_vertex_ids() {
+                d.initialize_vertex(id);
+            }
+            d.calculate_shortest_paths(&g, source).unwrap();
+            for target in g.get_vertex_ids() {
+                let mut b = BidirectionalDijkstra::new();
+                let result = b.calculate_shortest_path(&g, source, target).unwrap();
+                assert_eq!(result.distance,d.get_shortest_path_distance(target));
+                if let Value(distance) = result.distance {
+                    assert_eq!(g.verify_path(result.path.clone()),Some(distance));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn path_and_unreachable() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut b = BidirectionalDijkstra::new();
+        let result = b.calculate_shortest_path(&g, 1, 5).unwrap();
+        assert_eq!(result.distance,Value(20));
+        assert_eq!(result.path,vec!(1,3,6,5));
+        let result = b.calculate_shortest_path(&g, 1, 7).unwrap();
+        assert_eq!(result.distance,Max);
+        assert_eq!(result.path,vec!(7));
+        assert!(b.calculate_shortest_path(&g, 1, 8).is_err());
+    }
+
+}
@@ -2,7 +2,7 @@ extern crate clap;
 //use log::{ info , error /* ,debug, warn,trace */ };
 
 //use clap::{Arg, Command,arg, Parser, Subcommand};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 /*
 #[derive(Debug)]
@@ -45,47 +45,295 @@ pub enum Commands {
         #[clap(short, long, action)]
         #[clap(short, long, value_parser, use_value_delimiter=true)]
         /// list of vertexes to display
-        path: Vec<usize>,
+        path: Vec<String>,
     },
     /// Executes Dijkstra shortest path on graph
     Dijkstra {
-        #[clap(value_parser)]
-        start: usize,
+        #[clap(value_parser, required_unless_present("sources"))]
+        /// starting vertex (id or label)
+        start: Option<String>,
+
+        #[clap(long, value_parser=parse_source, use_value_delimiter=true, conflicts_with_all(&["start","target"]))]
+        /// starts from the nearest of several vertexes, each optionally with an initial
+        /// distance (e.g. 1,5:10,9)
+        sources: Option<Vec<(String,i64)>>,
 
         #[clap(short, long, value_parser, use_value_delimiter=true)]
         /// list of vertexes to display
-        display_list: Option<Vec<usize>>,
+        display_list: Option<Vec<String>>,
 
         #[clap(short, long, takes_value=false)]
         /// displays the path
         show_paths: bool,
+
+        #[clap(short, long, value_parser, conflicts_with_all(&["display-list","show-paths","queue"]))]
+        /// finds only the path to this vertex using a bidirectional search (which always shows
+        /// the path and has its own queue)
+        target: Option<String>,
+
+        #[clap(long, takes_value=false)]
+        /// runs even if the graph has negative edge weights (the results may be incorrect)
+        allow_negative: bool,
+
+        #[clap(short, long, value_enum, default_value="min-heap")]
+        /// priority queue used for the unprocessed vertexes
+        queue: QueueKind,
     },
-    Bellman {
+    /// Executes delta-stepping shortest paths on graph (non-negative weights only), relaxing
+    /// the edges of each bucket of vertexes on multiple threads
+    Delta {
         #[clap(value_parser)]
-        start: usize,
+        /// starting vertex (id or label)
+        start: String,
+
+        #[clap(short, long, value_parser, use_value_delimiter=true)]
+        /// list of vertexes to display
+        display_list: Option<Vec<String>>,
+
+        #[clap(long, value_parser, default_value_t=0)]
+        /// bucket width (0 picks one from the edge weights and degrees of the graph)
+        delta: i64,
+
+        #[clap(short, long, value_parser, min_values=0, default_missing_value="0")]
+        /// runs the relaxations on multiple threads (defaults to the number of cores)
+        threads: Option<usize>,
+    },
+    Bellman {
+        #[clap(value_parser, required_unless_present("sources"))]
+        /// starting vertex (id or label)
+        start: Option<String>,
+
+        #[clap(long, value_parser=parse_source, use_value_delimiter=true, conflicts_with("start"))]
+        /// starts from the nearest of several vertexes, each optionally with an initial
+        /// distance (e.g. 1,5:10,9)
+        sources: Option<Vec<(String,i64)>>,
 
         #[clap(short, long, value_parser, use_value_delimiter=true)]
         /// list of vertexes to display
-        display_list: Option<Vec<usize>>,
+        display_list: Option<Vec<String>>,
 
         #[clap(short, long, takes_value=false)]
         /// displays the path
         show_paths: bool,
+
+        #[clap(short, long, takes_value=false)]
+        /// use the queue based variant (SPFA) which only relaxes edges of changed vertexes
+        queue: bool,
+
+        #[clap(long, takes_value=false)]
+        /// always runs Bellman-Ford (by default acyclic graphs are solved in topological order)
+        no_dag: bool,
         
     },
-    Johnson {
+    /// Executes shortest (or longest) paths on an acyclic graph in topological order
+    Dag {
+        #[clap(value_parser, required_unless_present("sources"))]
+        /// starting vertex (id or label)
+        start: Option<String>,
+
+        #[clap(long, value_parser=parse_source, use_value_delimiter=true, conflicts_with("start"))]
+        /// starts from the nearest of several vertexes, each optionally with an initial
+        /// distance (e.g. 1,5:10,9)
+        sources: Option<Vec<(String,i64)>>,
+
         #[clap(short, long, value_parser, use_value_delimiter=true)]
         /// list of vertexes to display
-        display_list: Option<Vec<usize>>,
+        display_list: Option<Vec<String>>,
 
         #[clap(short, long, takes_value=false)]
         /// displays the path
         show_paths: bool,
+
+        #[clap(short, long, takes_value=false)]
+        /// finds the longest paths instead of the shortest
+        longest: bool,
+    },
+    Johnson {
+        #[clap(short, long, value_parser, use_value_delimiter=true, requires("all"))]
+        /// list of vertexes to display (limits the sources and destinations shown, requires --all)
+        display_list: Option<Vec<String>>,
+
+        #[clap(short, long, takes_value=false)]
+        /// displays the path
+        show_paths: bool,
+
+        #[clap(short, long, takes_value=false)]
+        /// displays the shortest paths between all pairs of vertexes instead of only the shortest one
+        all: bool,
+
+        #[clap(short, long, value_parser, default_value_t=0)]
+        /// number of threads used for the calculations from each source vertex (0 uses the
+        /// number of cores, 1 runs them serially)
+        threads: usize,
         
     },
+    /// Executes Floyd-Warshall all pairs shortest path on graph
+    Floyd {
+        #[clap(short, long, value_parser, use_value_delimiter=true, requires("all"))]
+        /// list of vertexes to display (limits the sources and destinations shown, requires --all)
+        display_list: Option<Vec<String>>,
+
+        #[clap(short, long, takes_value=false)]
+        /// displays the path
+        show_paths: bool,
+
+        #[clap(short, long, takes_value=false)]
+        /// displays the shortest paths between all pairs of vertexes instead of only the shortest one
+        all: bool,
+    },
+    /// Executes A* search for the shortest path between two vertexes
+    Astar {
+        #[clap(value_parser)]
+        /// starting vertex (id or label)
+        source: String,
+
+        #[clap(value_parser)]
+        /// target vertex (id or label)
+        target: String,
+
+        #[clap(short='e', long, value_enum, default_value="zero")]
+        /// heuristic used to estimate the distance to the target
+        heuristic: HeuristicKind,
+
+        #[clap(short, long, value_parser, use_value_delimiter=true, required_if_eq("heuristic","landmark"))]
+        /// list of landmark vertexes (for the landmark heuristic)
+        landmarks: Option<Vec<String>>,
+
+        #[clap(short, long, value_parser, required_if_eq("heuristic","coordinate"))]
+        /// file with the x y coordinates of each vertex (for the coordinate heuristic)
+        coordinates: Option<String>,
+    },
+    /// Executes Yen's algorithm for the k shortest loopless paths between two vertexes
+    Kpaths {
+        #[clap(value_parser)]
+        /// starting vertex (id or label)
+        source: String,
+
+        #[clap(value_parser)]
+        /// target vertex (id or label)
+        target: String,
+
+        #[clap(short, value_parser, default_value_t=3)]
+        /// number of paths to find
+        k: usize,
+    },
+    /// Finds the strongly connected components and prints the sizes of the largest
+    Scc {
+        #[clap(short, long, value_parser, default_value_t=5)]
+        /// number of component sizes to print
+        top: usize,
+    },
+    /// Finds the strongly connected components that have a negative cycle
+    Negcycles {
+        #[clap(short, long, value_parser, min_values=0, default_missing_value="0")]
+        /// checks the components in parallel on this many threads (all cores if no count given)
+        threads: Option<usize>,
+    },
+    /// Finds the cycle with the lowest mean edge weight (Karp's algorithm)
+    MinMeanCycle {},
+    /// Builds a contraction hierarchy of the graph (which must not have negative weights) and
+    /// saves it for later queries
+    PreprocessCh {
+        #[clap(value_parser)]
+        /// file the hierarchy is written to
+        output: String,
+    },
+    /// Finds the shortest path between two vertexes using a saved contraction hierarchy
+    QueryCh {
+        #[clap(value_parser)]
+        /// file with the hierarchy saved by preprocess-ch for this graph
+        hierarchy: String,
+
+        #[clap(value_parser)]
+        /// starting vertex (id or label)
+        source: String,
+
+        #[clap(value_parser)]
+        /// target vertex (id or label)
+        target: String,
+    },
+    /// Chooses landmarks and saves the distances to and from them (the graph must not have
+    /// negative weights) for later A* queries
+    PreprocessAlt {
+        #[clap(value_parser)]
+        /// file the landmark distances are written to
+        output: String,
+
+        #[clap(short, long, value_enum, default_value="farthest")]
+        /// how the landmarks are chosen
+        selection: LandmarkKind,
+
+        #[clap(short='n', long, value_parser, default_value_t=4)]
+        /// number of landmarks to choose (for farthest and random selection)
+        count: usize,
+
+        #[clap(short, long, value_parser, use_value_delimiter=true, required_if_eq("selection","given"))]
+        /// list of landmark vertexes (for given selection)
+        landmarks: Option<Vec<String>>,
+    },
+    /// Finds the shortest path between two vertexes with A* using saved landmark distances
+    QueryAlt {
+        #[clap(value_parser)]
+        /// file with the landmark distances saved by preprocess-alt for this graph
+        tables: String,
+
+        #[clap(value_parser)]
+        /// starting vertex (id or label)
+        source: String,
+
+        #[clap(value_parser)]
+        /// target vertex (id or label)
+        target: String,
+    },
+    /// Solves the system of difference constraints in the file (x - y <= c per line) instead
+    /// of reading a graph, printing a value for each variable or the infeasible constraints
+    Constraints {},
     Print {},
 }
 
+/// Parses a source vertex given as the vertex name optionally followed by :offset
+fn parse_source(value: &str) -> Result<(String,i64), String> {
+    match value.rsplit_once(':') {
+        Some((name, offset)) => match offset.trim().parse::<i64>() {
+            Ok(offset) => Ok((name.trim().to_string(), offset)),
+            Err(_) => Err(format!("invalid offset '{}' for source {}",offset,name)),
+        },
+        None => Ok((value.trim().to_string(), 0)),
+    }
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum HeuristicKind {
+    /// no estimate (same as Dijkstra)
+    Zero,
+    /// lower bound from the distances from landmark vertexes
+    Landmark,
+    /// straight line distance between vertex coordinates
+    Coordinate,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum QueueKind {
+    /// indexed binary heap that updates entries in place
+    MinHeap,
+    /// binary heap that adds a new entry when a distance is lowered
+    LazyHeap,
+    /// Dial's buckets (one per distance), for small integer weights
+    Dial,
+    /// radix heap, for integer weights
+    Radix,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum LandmarkKind {
+    /// each landmark is the vertex farthest from the ones already chosen
+    Farthest,
+    /// landmarks chosen at random
+    Random,
+    /// the vertexes given with --landmarks
+    Given,
+}
+
 /*
 #[derive(Debug)]
 pub struct CommandArgs  {
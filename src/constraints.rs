@@ -0,0 +1,192 @@
+use std::collections::{BTreeMap};
+use std::fmt;
+
+use crate::dirgraph::DirectedGraph;
+use crate::graphbuilder::GraphBuilder;
+use crate::bellman::Bellman;
+use crate::negcycle::NegativeCycleSearch;
+use crate::error::{Error, Result};
+
+use log::{ info, debug };
+use crate::minmax::MinMax::Value;
+
+/// A difference constraint x_j - x_i <= bound
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Constraint {
+    /// variable x_j
+    pub left: String,
+    /// variable x_i
+    pub right: String,
+    pub bound: i64,
+    /// line of the input the constraint was read from (0 if not read from a file)
+    pub line: usize,
+}
+
+impl fmt::Display for Constraint {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {} <= {}", self.left, self.right, self.bound)
+    }
+
+}
+
+/// Result of solving a system of difference constraints
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub enum ConstraintSolution {
+    /// value of each variable satisfying all of the constraints
+    Feasible(BTreeMap<String,i64>),
+    /// constraints that can't all be satisfied together (their bounds add up to less than 0
+    /// around a cycle of the variables)
+    Infeasible(Vec<Constraint>),
+}
+
+/// System of difference constraints x_j - x_i <= c solved with Bellman-Ford.  Each variable is
+/// a vertex of the constraint graph and each constraint an edge from x_i to x_j with weight c,
+/// and a virtual source with 0 weight edges to every variable makes the shortest distances a
+/// solution (or gives a negative cycle if there is none)
+#[derive(Debug,Clone,Default)]
+pub struct DifferenceConstraints {
+    /// variables in the order they were first declared or used
+    variables: Vec<String>,
+    constraints: Vec<Constraint>,
+}
+
+impl DifferenceConstraints {
+
+    pub fn new() -> DifferenceConstraints {
+        DifferenceConstraints { variables: Vec::<String>::new(), constraints: Vec::<Constraint>::new() }
+    }
+
+    /// Declares a variable (variables are also declared by the constraints using them)
+    pub fn add_variable(&mut self, name: &str) {
+        if !self.variables.iter().any(|v| v == name) {
+            self.variables.push(name.to_string());
+        }
+    }
+
+    /// Adds the constraint left - right <= bound
+    pub fn add_constraint(&mut self, left: &str, right: &str, bound: i64, line: usize) {
+        self.add_variable(left);
+        self.add_variable(right);
+        self.constraints.push(Constraint { left: left.to_string(), right: right.to_string(), bound, line });
+    }
+
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Finds values of the variables satisfying all of the constraints, or a set of
+    /// constraints that can't be satisfied
+    /// Returns NegativeCycle if the constraints are infeasible but no cycle of them was found
+    pub fn solve(&self) -> Result<ConstraintSolution> {
+        info!("Solving {} constraints on {} variables",self.constraints.len(),self.variables.len());
+        let mut graph = DirectedGraph::new();
+        for name in &self.variables {
+            graph.define_labeled_vertex(name);
+        }
+        // edge id -> position of the constraint it represents
+        let mut edge_constraint = BTreeMap::<usize,usize>::new();
+        for (pos, constraint) in self.constraints.iter().enumerate() {
+            let source = graph.vertex_by_label(&constraint.right).unwrap();
+            let dest = graph.vertex_by_label(&constraint.left).unwrap();
+            if let Some(edge_id) = (&mut graph).add_edge(source, dest, constraint.bound) {
+                edge_constraint.insert(edge_id, pos);
+            }
+        }
+        let virtual_source = graph.next_vertex_id();
+        for id in graph.get_vertex_ids() {
+            (&mut graph).add_edge(virtual_source, id, 0);
+        }
+
+        let mut bellman = Bellman::new();
+        bellman.calculate_shortest_paths(&graph, virtual_source)?;
+        if bellman.has_negative_cycle() {
+            // fall back to searching each strongly connected component if the cycle couldn't
+            // be recovered from the predecessors
+            let cycle = match bellman.negative_cycle() {
+                Some(cycle) => cycle,
+                None => match NegativeCycleSearch::new(&graph).find_negative_cycles()?.into_iter().next() {
+                    Some(found) => found.cycle,
+                    None => return Err(Error::NegativeCycle),
+                },
+            };
+            // the virtual source has no incoming edges, so the cycle only has constraint edges
+            let infeasible = cycle.edges.iter().filter_map(|id| edge_constraint.get(id)).map(|pos| self.constraints[*pos].clone()).collect();
+            info!("Constraints are infeasible");
+            return Ok(ConstraintSolution::Infeasible(infeasible));
+        }
+
+        let mut assignment = BTreeMap::<String,i64>::new();
+        for name in &self.variables {
+            if let Value(value) = bellman.get_shortest_path_distance(graph.vertex_by_label(name).unwrap()) {
+                debug!("{} = {}",name,value);
+                assignment.insert(name.clone(), value);
+            }
+        }
+        Ok(ConstraintSolution::Feasible(assignment))
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::constraints::{DifferenceConstraints, ConstraintSolution};
+
+    // example system from CLRS 24.4 (x5 - x4 <= -3 etc.), numbered as lines 1-8
+	fn clrs_system() -> DifferenceConstraints {
+        let mut system = DifferenceConstraints::new();
+        for (line, (left, right, bound)) in [("x1","x2",0),("x1","x5",-1),("x2","x5",1),("x3","x1",5),("x4","x1",4),("x4","x3",-1),("x5","x3",-3),("x5","x4",-3)].into_iter().enumerate() {
+            system.add_constraint(left,right,bound,line + 1);
+        }
+        system
+	}
+
+    #[test]
+    fn feasible() {
+        let system = clrs_system();
+        match system.solve().unwrap() {
+            ConstraintSolution::Feasible(values) => {
+                assert_eq!(values["x1"],-5);
+                assert_eq!(values["x5"],-4);
+                for c in system.constraints() {
+                    assert!(values[&c.left] - values[&c.right] <= c.bound, "{} not satisfied",c);
+                }
+            },
+            ConstraintSolution::Infeasible(_) => panic!("system should be feasible"),
+        }
+    }
+
+    #[test]
+    fn infeasible() {
+        let mut system = clrs_system();
+        system.add_constraint("x3","x5",2,9);
+        match system.solve().unwrap() {
+            ConstraintSolution::Infeasible(constraints) => {
+                // both x5 - x3 <= -3 and x4 - x3 <= -1, x5 - x4 <= -3 contradict the new constraint
+                let mut lines : Vec<usize> = constraints.iter().map(|c| c.line).collect();
+                lines.sort_unstable();
+                assert!(lines == vec!(7,9) || lines == vec!(6,8,9), "unexpected constraints {:?}",lines);
+                assert!(constraints.iter().map(|c| c.bound).sum::<i64>() < 0);
+            },
+            ConstraintSolution::Feasible(_) => panic!("system should be infeasible"),
+        }
+    }
+
+    #[test]
+    fn unconstrained_variable() {
+        // a declared variable with no constraints is left at 0
+        let mut system = DifferenceConstraints::new();
+        system.add_variable("y");
+        system.add_constraint("a","b",-2,1);
+        assert_eq!(system.variables(),&["y".to_string(),"a".to_string(),"b".to_string()]);
+        match system.solve().unwrap() {
+            ConstraintSolution::Feasible(values) => assert_eq!((values["y"],values["a"],values["b"]),(0,-2,0)),
+            ConstraintSolution::Infeasible(_) => panic!("system should be feasible"),
+        }
+    }
+
+}
@@ -0,0 +1,557 @@
+extern crate minheap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use minheap::MinHeap;
+use regex::Regex;
+
+use crate::dirgraph::DirectedGraph;
+
+use log::{ info, debug, error, warn, trace };
+use crate::minmax::{MinMax,MinMax::Value};
+use crate::shortpathinfo::ShortestPathInfo;
+use crate::parse::parse_field;
+use crate::error::{Error, Result};
+
+/// witness searches stop after settling this many vertexes, which may add a shortcut that
+/// isn't needed but never leaves out one that is
+const WITNESS_SETTLE_LIMIT: usize = 100;
+
+/// Edge of the hierarchy -- either an edge of the graph or a shortcut through a vertex
+/// contracted before both of its ends
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub struct HierarchyEdge {
+    pub weight: i64,
+    /// vertex the shortcut replaces (None for an edge of the graph)
+    pub middle: Option<usize>,
+}
+
+// graph of the vertexes not contracted yet, with the lowest weight edge between each pair
+struct Remaining {
+    outgoing: BTreeMap<usize,BTreeMap<usize,i64>>,
+    incoming: BTreeMap<usize,BTreeMap<usize,i64>>,
+}
+
+impl Remaining {
+
+    // distances from source found by a Dijkstra search that skips the excluded vertex, stopping
+    // once all of the targets are settled or past the limit distance (any of the distances is
+    // the weight of a real path)
+    fn witness_distances(&self, source: usize, excluded: usize, targets: &BTreeMap<usize,i64>, limit: i64) -> BTreeMap<usize,i64> {
+        let mut open_vertex = MinHeap::<i64>::new();
+        open_vertex.insert(source, 0);
+        let mut distance = BTreeMap::from([(source, 0)]);
+        let mut settled = BTreeSet::<usize>::new();
+        let mut targets_left = targets.len();
+        while let Some((vertex, cur_distance)) = open_vertex.get_min_entry() {
+            if cur_distance > limit || settled.len() >= WITNESS_SETTLE_LIMIT {
+                break;
+            }
+            settled.insert(vertex);
+            if targets.contains_key(&vertex) {
+                targets_left -= 1;
+                if targets_left == 0 {
+                    break;
+                }
+            }
+            for (next, weight) in &self.outgoing[&vertex] {
+                if *next == excluded || settled.contains(next) {
+                    continue;
+                }
+                let new_distance = cur_distance + weight;
+                match distance.get(next) {
+                    Some(d) if new_distance >= *d => (),
+                    Some(_) => {
+                        let index = *open_vertex.get_id_index(*next).unwrap();
+                        open_vertex.update(index, new_distance);
+                        distance.insert(*next, new_distance);
+                    },
+                    None => {
+                        open_vertex.insert(*next, new_distance);
+                        distance.insert(*next, new_distance);
+                    },
+                }
+            }
+        }
+        distance
+    }
+
+    // shortcuts (source, dest, weight) needed to keep the distances between the other
+    // vertexes when the vertex is removed -- one for each path in and out of the vertex without
+    // a path at least as short around it
+    fn needed_shortcuts(&self, vertex: usize) -> Vec<(usize,usize,i64)> {
+        let mut shortcuts = Vec::<(usize,usize,i64)>::new();
+        let outgoing = &self.outgoing[&vertex];
+        let max_out = match outgoing.values().max() {
+            Some(max_out) => *max_out,
+            None => return shortcuts,
+        };
+        for (source, in_weight) in &self.incoming[&vertex] {
+            let witness = self.witness_distances(*source, vertex, outgoing, in_weight + max_out);
+            for (dest, out_weight) in outgoing {
+                if dest == source {
+                    continue;
+                }
+                let weight = in_weight + out_weight;
+                match witness.get(dest) {
+                    Some(d) if *d <= weight => (),
+                    _ => shortcuts.push((*source, *dest, weight)),
+                }
+            }
+        }
+        shortcuts
+    }
+
+    fn set_edge(&mut self, source: usize, dest: usize, weight: i64) {
+        self.outgoing.get_mut(&source).unwrap().insert(dest, weight);
+        self.incoming.get_mut(&dest).unwrap().insert(source, weight);
+    }
+
+    fn remove_vertex(&mut self, vertex: usize) {
+        for dest in self.outgoing.remove(&vertex).unwrap().keys() {
+            self.incoming.get_mut(dest).unwrap().remove(&vertex);
+        }
+        for source in self.incoming.remove(&vertex).unwrap().keys() {
+            self.outgoing.get_mut(source).unwrap().remove(&vertex);
+        }
+    }
+}
+
+// State of the upward search in one direction -- forward from the source on the edges to
+// higher ranked vertexes or backward from the target on the edges from them
+struct UpwardSearch {
+    open_vertex : MinHeap::<i64>,
+    distance: BTreeMap<usize,i64>,
+    settled: BTreeSet<usize>,
+    /// next vertex towards the start of this side's search
+    predecessor: BTreeMap<usize,usize>,
+    done: bool,
+}
+
+impl UpwardSearch {
+
+    fn new(start: usize) -> UpwardSearch {
+        let mut open_vertex = MinHeap::<i64>::new();
+        open_vertex.insert(start, 0);
+        UpwardSearch {
+            open_vertex,
+            distance: BTreeMap::from([(start, 0)]),
+            settled: BTreeSet::<usize>::new(),
+            predecessor: BTreeMap::<usize,usize>::new(),
+            done: false,
+        }
+    }
+
+    // settles the closest open vertex and relaxes its edges, updating the best path found
+    // (distance and meeting vertex) when the other side has also reached it.  The search is
+    // done once the closest vertex is no closer than the best path
+    fn step(&mut self, other: &UpwardSearch, edges: &BTreeMap<usize,Vec<(usize,i64)>>, best: &mut (MinMax<i64>, Option<usize>)) {
+        let (vertex, distance) = match self.open_vertex.get_min_entry() {
+            Some((vertex, distance)) if Value(distance) < best.0 => (vertex, distance),
+            _ => {
+                self.done = true;
+                return;
+            }
+        };
+        self.settled.insert(vertex);
+        if let Some(other_distance) = other.distance.get(&vertex) {
+            if Value(distance + other_distance) < best.0 {
+                debug!("Upward searches meet at {} with distance {}",vertex,distance + other_distance);
+                *best = (Value(distance + other_distance), Some(vertex));
+            }
+        }
+        for (next, weight) in edges.get(&vertex).map(|list| &list[..]).unwrap_or_default() {
+            if self.settled.contains(next) {
+                continue;
+            }
+            let new_distance = distance + weight;
+            match self.distance.get(next) {
+                Some(d) if new_distance >= *d => (),
+                Some(_) => {
+                    let index = *self.open_vertex.get_id_index(*next).unwrap();
+                    self.open_vertex.update(index, new_distance);
+                    self.distance.insert(*next, new_distance);
+                    self.predecessor.insert(*next, vertex);
+                },
+                None => {
+                    self.open_vertex.insert(*next, new_distance);
+                    self.distance.insert(*next, new_distance);
+                    self.predecessor.insert(*next, vertex);
+                },
+            }
+        }
+    }
+
+    // vertexes of the hierarchy from the start of this side's search to vertex (in the order
+    // found by this side)
+    fn path_to(&self, vertex: usize) -> Vec<usize> {
+        let mut path = vec!(vertex);
+        let mut current = vertex;
+        while let Some(prev) = self.predecessor.get(&current) {
+            path.push(*prev);
+            current = *prev;
+        }
+        path
+    }
+}
+
+/// Contraction hierarchy for repeated point to point queries on a graph with non-negative
+/// weights.  Preprocessing contracts the vertexes one at a time (fewest shortcuts added first),
+/// adding a shortcut between the neighbors of the vertex whenever the path through it is the
+/// only shortest one.  A query then only searches upward in the order from both ends, and the
+/// shortcuts on the path found are unpacked back to the vertexes of the graph.  Works best on
+/// sparse, road-like graphs -- dense graphs need many shortcuts and take much longer to build
+#[derive(Debug,Clone)]
+pub struct ContractionHierarchy {
+    /// position of each vertex in the contraction order (first contracted is 0)
+    rank: BTreeMap<usize,usize>,
+    /// lowest weight edge of the hierarchy (graph edges and shortcuts) between each pair
+    edges: BTreeMap<(usize,usize),HierarchyEdge>,
+    /// edges to higher ranked vertexes (by source)
+    upward: BTreeMap<usize,Vec<(usize,i64)>>,
+    /// edges from higher ranked vertexes (by dest, giving the source)
+    downward: BTreeMap<usize,Vec<(usize,i64)>>,
+    settled_count: usize,
+}
+
+impl ContractionHierarchy {
+
+    /// Builds the hierarchy of the graph
+    /// Returns NegativeEdges if the graph has negative edge weights
+    pub fn build(graph: &DirectedGraph) -> Result<ContractionHierarchy> {
+        let negative_edges = graph.get_negative_edge_ids();
+        if !negative_edges.is_empty() {
+            warn!("Graph has {} edges with negative weights",negative_edges.len());
+            return Err(Error::NegativeEdges(negative_edges));
+        }
+        info!("Building contraction hierarchy of {} vertexes and {} edges",graph.vertex_count(),graph.edge_count());
+
+        let mut remaining = Remaining { outgoing: BTreeMap::new(), incoming: BTreeMap::new() };
+        for id in graph.get_vertex_ids() {
+            remaining.outgoing.insert(id, BTreeMap::new());
+            remaining.incoming.insert(id, BTreeMap::new());
+        }
+        let mut edges = BTreeMap::<(usize,usize),HierarchyEdge>::new();
+        for (_id, e) in graph.edge_iter() {
+            // loops are never part of a shortest path
+            if e.source() == e.dest() {
+                continue;
+            }
+            let better = match edges.get(&(e.source(), e.dest())) {
+                Some(edge) => e.weight() < edge.weight,
+                None => true,
+            };
+            if better {
+                edges.insert((e.source(), e.dest()), HierarchyEdge { weight: e.weight(), middle: None });
+                remaining.set_edge(e.source(), e.dest(), e.weight());
+            }
+        }
+
+        // the priority of a vertex is the number of shortcuts contracting it would add less the
+        // edges it removes, plus the neighbors already contracted (spreading the contractions
+        // out over the graph).  Priorities only go stale as neighbors are contracted, so they
+        // are updated when a vertex reaches the front of the queue
+        let mut contracted_neighbors = BTreeMap::<usize,i64>::new();
+        let priority = |remaining: &Remaining, contracted: &BTreeMap<usize,i64>, vertex: usize, shortcut_count: usize| {
+            let degree = remaining.outgoing[&vertex].len() + remaining.incoming[&vertex].len();
+            shortcut_count as i64 - degree as i64 + contracted.get(&vertex).cloned().unwrap_or(0)
+        };
+        let mut queue : BTreeSet<(i64,usize)> = graph.get_vertex_ids().into_iter()
+            .map(|id| (priority(&remaining, &contracted_neighbors, id, remaining.needed_shortcuts(id).len()), id))
+            .collect();
+
+        let mut rank = BTreeMap::<usize,usize>::new();
+        while let Some((old_priority, vertex)) = queue.pop_first() {
+            let shortcuts = remaining.needed_shortcuts(vertex);
+            let new_priority = priority(&remaining, &contracted_neighbors, vertex, shortcuts.len());
+            if new_priority > old_priority {
+                if let Some((next_priority, _next)) = queue.first() {
+                    if new_priority > *next_priority {
+                        queue.insert((new_priority, vertex));
+                        continue;
+                    }
+                }
+            }
+
+            trace!("Contracting {} (rank {}) with {} shortcuts",vertex,rank.len(),shortcuts.len());
+            for (source, dest, weight) in shortcuts {
+                let better = match edges.get(&(source, dest)) {
+                    Some(edge) => weight < edge.weight,
+                    None => true,
+                };
+                if better {
+                    edges.insert((source, dest), HierarchyEdge { weight, middle: Some(vertex) });
+                    remaining.set_edge(source, dest, weight);
+                }
+            }
+            let neighbors : BTreeSet<usize> = remaining.outgoing[&vertex].keys().chain(remaining.incoming[&vertex].keys()).cloned().collect();
+            for neighbor in neighbors {
+                *contracted_neighbors.entry(neighbor).or_insert(0) += 1;
+            }
+            remaining.remove_vertex(vertex);
+            rank.insert(vertex, rank.len());
+        }
+
+        let hierarchy = ContractionHierarchy::from_edges(rank, edges)?;
+        info!("Hierarchy has {} edges including {} shortcuts",hierarchy.edge_count(),hierarchy.shortcut_count());
+        Ok(hierarchy)
+    }
+
+    /// Creates the hierarchy from the rank of each vertex and its edges (e.g. as read back
+    /// from a saved hierarchy)
+    /// Returns UnknownVertex if an edge or shortcut uses a vertex without a rank
+    pub fn from_edges(rank: BTreeMap<usize,usize>, edges: BTreeMap<(usize,usize),HierarchyEdge>) -> Result<ContractionHierarchy> {
+        let mut upward = BTreeMap::<usize,Vec<(usize,i64)>>::new();
+        let mut downward = BTreeMap::<usize,Vec<(usize,i64)>>::new();
+        for ((source, dest), edge) in &edges {
+            for vertex in [Some(*source), Some(*dest), edge.middle].into_iter().flatten() {
+                if !rank.contains_key(&vertex) {
+                    return Err(Error::UnknownVertex(vertex));
+                }
+            }
+            if rank[source] < rank[dest] {
+                upward.entry(*source).or_default().push((*dest, edge.weight));
+            }
+            else {
+                downward.entry(*dest).or_default().push((*source, edge.weight));
+            }
+        }
+        Ok(ContractionHierarchy { rank, edges, upward, downward, settled_count: 0 })
+    }
+
+    /// Writes the hierarchy as a line for each vertex (vertex id rank) followed by a line for
+    /// each edge (edge source dest weight), with the middle vertex added for shortcuts
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "# contraction hierarchy: {} vertexes, {} edges, {} shortcuts", self.vertex_count(), self.edge_count(), self.shortcut_count())?;
+        for (vertex, rank) in &self.rank {
+            writeln!(writer, "vertex {} {}", vertex, rank)?;
+        }
+        for ((source, dest), edge) in &self.edges {
+            match edge.middle {
+                Some(middle) => writeln!(writer, "edge {} {} {} {}", source, dest, edge.weight, middle)?,
+                None => writeln!(writer, "edge {} {} {}", source, dest, edge.weight)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a hierarchy written by save.  Lines starting with # are comments
+    /// e.g.
+    ///
+    /// vertex 1 0
+    /// vertex 2 1
+    /// edge 1 2 7
+    /// edge 2 3 12 1
+    pub fn load<R: Read>(reader: R) -> Result<ContractionHierarchy> {
+        let reader = BufReader::new(reader);
+        let re_vertex = Regex::new(r"^\s*vertex\s+(?P<vertex>\d+)\s+(?P<rank>\d+)\s*$").unwrap();
+        let re_edge = Regex::new(r"^\s*edge\s+(?P<source>\d+)\s+(?P<dest>\d+)\s+(?P<weight>-?\d+)(\s+(?P<middle>\d+))?\s*$").unwrap();
+
+        let mut rank = BTreeMap::<usize,usize>::new();
+        let mut edges = BTreeMap::<(usize,usize),HierarchyEdge>::new();
+        for (line_index, line) in reader.lines().enumerate() {
+            let line_count = line_index + 1;
+            let line_data = line?;
+            if line_data.trim().is_empty() || line_data.trim_start().starts_with('#') {
+                continue;
+            }
+            if let Some(caps) = re_vertex.captures(&line_data) {
+                let vertex_match = caps.name("vertex").unwrap();
+                let rank_match = caps.name("rank").unwrap();
+                let vertex = parse_field::<usize>(vertex_match.as_str(), line_count, vertex_match.start())?;
+                rank.insert(vertex, parse_field::<usize>(rank_match.as_str(), line_count, rank_match.start())?);
+            }
+            else if let Some(caps) = re_edge.captures(&line_data) {
+                let mut fields = Vec::<usize>::new();
+                for name in ["source", "dest"] {
+                    let field_match = caps.name(name).unwrap();
+                    fields.push(parse_field::<usize>(field_match.as_str(), line_count, field_match.start())?);
+                }
+                let weight_match = caps.name("weight").unwrap();
+                let weight = parse_field::<i64>(weight_match.as_str(), line_count, weight_match.start())?;
+                let middle = match caps.name("middle") {
+                    Some(middle_match) => Some(parse_field::<usize>(middle_match.as_str(), line_count, middle_match.start())?),
+                    None => None,
+                };
+                trace!("Edge {} -> {} weight {} middle {:?}",fields[0],fields[1],weight,middle);
+                edges.insert((fields[0], fields[1]), HierarchyEdge { weight, middle });
+            }
+            else {
+                error!("Line {} - No vertex or edge found ({})",line_count,line_data);
+                let column = line_data.len() - line_data.trim_start().len();
+                return Err(Error::Parse { line: line_count, column: column + 1, message: "expected vertex or edge".to_string() });
+            }
+        }
+        info!("Read hierarchy of {} vertexes and {} edges",rank.len(),edges.len());
+        ContractionHierarchy::from_edges(rank, edges)
+    }
+
+    /// Finds the shortest path from source to target.  The distance of the result is Max if
+    /// the target can't be reached from the source
+    pub fn calculate_shortest_path(&mut self, source: usize, target: usize) -> Result<ShortestPathInfo> {
+        for vertex in [source, target] {
+            if !self.rank.contains_key(&vertex) {
+                warn!("Vertex {} is not in the hierarchy",vertex);
+                return Err(Error::UnknownVertex(vertex));
+            }
+        }
+
+        let mut forward = UpwardSearch::new(source);
+        let mut backward = UpwardSearch::new(target);
+        let mut best = (MinMax::Max, None);
+        let mut forward_turn = true;
+        while !(forward.done && backward.done) {
+            if (forward_turn && !forward.done) || backward.done {
+                forward.step(&backward, &self.upward, &mut best);
+            }
+            else {
+                backward.step(&forward, &self.downward, &mut best);
+            }
+            forward_turn = !forward_turn;
+        }
+        self.settled_count = forward.settled.len() + backward.settled.len();
+
+        info!("Hierarchy search settled {} vertexes, distance from {} to {} is {}",self.settled_count,source,target,best.0);
+        let meet = match best.1 {
+            Some(meet) => meet,
+            None => return Ok(ShortestPathInfo::unreachable(source, target)),
+        };
+        let mut hierarchy_path = forward.path_to(meet);
+        hierarchy_path.reverse();
+        hierarchy_path.extend(backward.path_to(meet).into_iter().skip(1));
+        let mut path = vec!(source);
+        for pair in hierarchy_path.windows(2) {
+            self.unpack(pair[0], pair[1], &mut path);
+        }
+        Ok(ShortestPathInfo {
+            source,
+            dest: target,
+            distance: best.0,
+            path_len: path.len(),
+            path,
+            has_negative_cycle: false,
+        })
+    }
+
+    // adds the vertexes of the graph after source on the path of the hierarchy edge from
+    // source to dest, replacing shortcuts by the two edges they were made from
+    fn unpack(&self, source: usize, dest: usize, path: &mut Vec<usize>) {
+        let mut stack = vec!((source, dest));
+        while let Some((from, to)) = stack.pop() {
+            match self.edges[&(from, to)].middle {
+                Some(middle) => {
+                    stack.push((middle, to));
+                    stack.push((from, middle));
+                },
+                None => path.push(to),
+            }
+        }
+    }
+
+    /// Position of the vertex in the contraction order
+    pub fn rank(&self, vertex: usize) -> Option<usize> {
+        self.rank.get(&vertex).cloned()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.rank.len()
+    }
+
+    /// Number of edges in the hierarchy (including the shortcuts)
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn shortcut_count(&self) -> usize {
+        self.edges.values().filter(|edge| edge.middle.is_some()).count()
+    }
+
+    /// Number of vertexes settled (in both directions) by the last query
+    pub fn settled_count(&self) -> usize {
+        self.settled_count
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::contraction::ContractionHierarchy;
+    use crate::dijkstra::Dijkstra;
+    use crate::minmax::MinMax::{Value,Max};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+    // 5 x 5 grid with edges both ways between neighbors (weights vary by position) plus an
+    // unreachable vertex 26
+	fn setup_grid(mut g :&mut DirectedGraph) {
+        for row in 0..5 {
+            for col in 0..5 {
+                let v = row * 5 + col + 1;
+                if col < 4 {
+                    g.add_edge(v, v + 1, ((v * 7) % 5 + 1) as i64);
+                    g.add_edge(v + 1, v, ((v * 3) % 4 + 1) as i64);
+                }
+                if row < 4 {
+                    g.add_edge(v, v + 5, ((v * 5) % 3 + 2) as i64);
+                    g.add_edge(v + 5, v, ((v * 11) % 6 + 1) as i64);
+                }
+            }
+        }
+		g.define_vertex(26);
+	}
+
+    fn check_matches_dijkstra(g: &DirectedGraph, ch: &mut ContractionHierarchy) {
+        for source in g.get_vertex_ids() {
+            let mut d = Dijkstra::new(source);
+            for id in g.get_vertex_ids() {
+                d.initialize_vertex(id);
+            }
+            d.calculate_shortest_paths(g, source).unwrap();
+            for target in g.get_vertex_ids() {
+                let result = ch.calculate_shortest_path(source, target).unwrap();
+                assert_eq!(result.distance,d.get_shortest_path_distance(target),"distance from {} to {}",source,target);
+                if let Value(distance) = result.distance {
+                    assert_eq!(g.verify_path(result.path.clone()),Some(distance));
+                    assert_eq!((result.path[0],result.path[result.path_len - 1]),(source,target));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_dijkstra() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+        let mut ch = ContractionHierarchy::build(&g).unwrap();
+        assert_eq!(ch.vertex_count(),26);
+        check_matches_dijkstra(&g, &mut ch);
+
+        let result = ch.calculate_shortest_path(1, 26).unwrap();
+        assert_eq!((result.distance,result.path),(Max,vec!(26)));
+        assert!(ch.calculate_shortest_path(1, 27).is_err());
+		(&mut g).add_edge(3,4,-1);
+        assert!(ContractionHierarchy::build(&g).is_err());
+    }
+
+    #[test]
+    fn save_and_load() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_grid(&mut g);
+        let ch = ContractionHierarchy::build(&g).unwrap();
+        let mut saved = Vec::<u8>::new();
+        ch.save(&mut saved).unwrap();
+        let mut loaded = ContractionHierarchy::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.edge_count(),ch.edge_count());
+        assert_eq!(loaded.shortcut_count(),ch.shortcut_count());
+        assert_eq!(loaded.rank(13),ch.rank(13));
+        check_matches_dijkstra(&g, &mut loaded);
+    }
+
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use log::{ debug, /*warn,*/ trace };
+
+use crate::minmax::MinMax;
+use crate::minmax::MinMax::Value;
+
+
+/// A cycle in a graph
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct Cycle {
+    /// vertexes of the cycle in order starting with the lowest vertex id -- the cycle continues
+    /// from the last vertex back to the first
+    pub vertexes: Vec<usize>,
+    /// ids of the edges of the cycle; edge i connects vertexes[i] to vertexes[i+1] (wrapping around)
+    pub edges: Vec<usize>,
+    /// total weight of all the edges of the cycle
+    pub weight: i64,
+}
+
+impl fmt::Display for Cycle {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vertex_list : Vec<String> = self.vertexes.iter()
+            .chain(self.vertexes.first())
+            .map(|v| format!("{}",v))
+            .collect();
+        let edge_list : Vec<String> = self.edges.iter().map(|e| format!("{}",e)).collect();
+        write!(f, "{} (edges {}) weight {}", vertex_list.join(" -> "), edge_list.join(","), self.weight)
+    }
+
+}
+
+impl Cycle {
+
+    /// Creates a cycle from its vertexes and edges in order (edge i leaving vertex i), rotated
+    /// to start at the lowest vertex id so the same cycle is always reported the same way
+    pub fn new(mut vertexes: Vec<usize>, mut edges: Vec<usize>, weight: i64) -> Cycle {
+        if let Some((start, _)) = vertexes.iter().enumerate().min_by_key(|(_, v)| **v) {
+            vertexes.rotate_left(start);
+            edges.rotate_left(start);
+        }
+        Cycle { vertexes, edges, weight }
+    }
+
+    /// average weight of the edges of the cycle
+    pub fn mean_weight(&self) -> f64 {
+        self.weight as f64 / self.len() as f64
+    }
+
+    /// number of edges (and vertexes) in the cycle
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Finds a cycle in a predecessor map as built by the shortest path algorithms, where each
+    /// vertex maps to the preceeding vertex in its path and predecessor_edge holds the id and
+    /// weight of the edge from that vertex.   The search starts with the vertexes in start_list
+    /// and then continues with the rest of the vertexes.  Returns None if there is no cycle
+    pub fn from_predecessors(predecessor: &BTreeMap<usize,MinMax<usize>>,
+                             predecessor_edge: &BTreeMap<usize,(usize,i64)>,
+                             start_list: &[usize]) -> Option<Cycle> {
+
+        // vertex -> the walk during which the vertex was visited
+        let mut visited = BTreeMap::<usize,usize>::new();
+
+        for (walk, start) in start_list.iter().chain(predecessor.keys()).enumerate() {
+            let mut current = *start;
+            // walk back through the predecessors until reaching a vertex without one, a
+            // vertex that was visited by an earlier walk or one already visited by this walk
+            // (in which case that vertex is on a cycle)
+            loop {
+                match visited.get(&current) {
+                    Some(w) if *w == walk => {
+                        debug!("Vertex {} found on a predecessor cycle",current);
+                        return Some(Cycle::trace(predecessor,predecessor_edge,current));
+                    },
+                    Some(_) => break,
+                    None => {
+                        visited.insert(current,walk);
+                    }
+                }
+                match predecessor.get(&current) {
+                    Some(Value(prev)) => current = *prev,
+                    _ => break,
+                }
+            }
+        }
+        None
+    }
+
+    // collects the vertexes and edges of the cycle that includes on_cycle
+    fn trace(predecessor: &BTreeMap<usize,MinMax<usize>>,
+             predecessor_edge: &BTreeMap<usize,(usize,i64)>,
+             on_cycle: usize) -> Cycle {
+
+        let mut vertexes = Vec::<usize>::new();
+        let mut edges = Vec::<usize>::new();
+        let mut weight = 0;
+
+        let mut current = on_cycle;
+        loop {
+            vertexes.push(current);
+            let (edge_id, edge_weight) = predecessor_edge[&current];
+            edges.push(edge_id);
+            weight += edge_weight;
+            if let Value(prev) = predecessor[&current] {
+                current = prev;
+            }
+            if current == on_cycle {
+                break;
+            }
+        }
+
+        // the predecessors were followed backwards, so reverse the order of the vertexes.  Edge
+        // i was the edge into vertex i, so after reversing, rotate by one so that edge i leaves
+        // vertex i
+        vertexes.reverse();
+        edges.reverse();
+        edges.rotate_left(1);
+
+        let cycle = Cycle::new(vertexes, edges, weight);
+        trace!("Traced cycle {:?} edges {:?} weight {}",cycle.vertexes,cycle.edges,cycle.weight);
+        cycle
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeMap};
+
+use crate::dirgraph::DirectedGraph;
+
+use log::{ info, debug, error, trace };
+use crate::minmax::{MinMax,MinMax::Value};
+use crate::shortpathinfo::ShortestPathInfo;
+use crate::error::{Error, Result};
+
+/// Shortest (or longest) paths on a directed acyclic graph.  The edges of each vertex are
+/// relaxed in topological order, so each edge is only relaxed once -- O(V+E) even with
+/// negative edge weights
+#[derive(Debug)]
+pub struct DagPaths<'a> {
+        graph: &'a DirectedGraph,
+        /// finds the longest paths instead of the shortest
+        longest: bool,
+        /// distance of the best path to each vertex (by dense index), Max if unreachable
+        distance: Vec<MinMax<i64>>,
+        /// preceeding vertex in the best path to each vertex (by dense index)
+        predecessor: Vec<Option<usize>>,
+        /// vertex ids of the graph in dense index order
+        vertex_ids: Vec<usize>,
+        starting_vertex: usize,
+}
+
+impl<'a> DagPaths<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> DagPaths<'a> {
+        DagPaths {
+            graph,
+            longest: false,
+            distance: Vec::<MinMax<i64>>::new(),
+            predecessor: Vec::<Option<usize>>::new(),
+            vertex_ids: graph.vertex_ids_by_index().to_vec(),
+            starting_vertex: 0,
+        }
+    }
+
+    /// Creates a solver for the longest paths (which are well defined on acyclic graphs)
+    pub fn with_longest_paths(graph: &'a DirectedGraph) -> DagPaths<'a> {
+        let mut dag = DagPaths::new(graph);
+        dag.longest = true;
+        dag
+    }
+
+    /// Find the best path from a starting vertex to all other vertexes in the graph
+    /// Returns NotAcyclic if the graph has a cycle
+    pub fn calculate_shortest_paths(&mut self, starting_vertex: usize) -> Result<()> {
+        self.calculate_shortest_paths_multi(&[(starting_vertex,0)])
+    }
+
+    /// Find the best path from the nearest of several sources, each given with an initial
+    /// offset added to the distance of paths starting from it
+    /// Returns NotAcyclic if the graph has a cycle
+    pub fn calculate_shortest_paths_multi(&mut self, sources: &[(usize,i64)]) -> Result<()> {
+        info!("Starting {} paths on acyclic graph from {:?}",if self.longest { "longest" } else { "shortest" },sources);
+        let n = self.graph.vertex_count();
+        self.distance = vec![MinMax::Max; n];
+        self.predecessor = vec![None; n];
+        for (source, offset) in sources {
+            let index = match self.graph.vertex_index(*source) {
+                Some(index) => index,
+                None => {
+                    error!("Starting vertex {} is not in the graph",source);
+                    return Err(Error::UnknownVertex(*source));
+                }
+            };
+            if self.is_better(Value(*offset), self.distance[index]) {
+                self.distance[index] = Value(*offset);
+            }
+        }
+        self.starting_vertex = sources.first().map_or(0, |(source, _offset)| *source);
+
+        let order = self.graph.topological_sort()?;
+        for id in order {
+            let index = self.graph.vertex_index(id).unwrap();
+            let cur_distance = self.distance[index];
+            if cur_distance == MinMax::Max {
+                continue;
+            }
+            for e in self.graph.get_outgoing_edges(id)? {
+                let new_distance = cur_distance + Value(e.weight());
+                if self.is_better(new_distance, self.distance[e.dest_index()]) {
+                    trace!("Vertex {} updated from {} to {} via {}",e.dest(),self.distance[e.dest_index()],new_distance,id);
+                    self.distance[e.dest_index()] = new_distance;
+                    self.predecessor[e.dest_index()] = Some(id);
+                }
+            }
+        }
+        debug!("Distances {:?}",self.distance);
+        Ok(())
+    }
+
+    // true if the new distance should replace the current one (any distance replaces Max,
+    // which marks vertexes not reached yet)
+    fn is_better(&self, new_distance: MinMax<i64>, cur_distance: MinMax<i64>) -> bool {
+        match cur_distance {
+            MinMax::Max => true,
+            _ if self.longest => new_distance > cur_distance,
+            _ => new_distance < cur_distance,
+        }
+    }
+
+    /// Returns the distance calculated to the dest_vertex
+    /// Returns NA if the dest_vertex is not in the graph or the paths haven't been calculated
+    pub fn get_shortest_path_distance(&self, dest_vertex: usize) -> MinMax<i64> {
+        match self.graph.vertex_index(dest_vertex) {
+            Some(index) if !self.distance.is_empty() => self.distance[index],
+            _ => MinMax::NA,
+        }
+    }
+
+    /// Returns the distances calculated to each of the vertexes
+    pub fn get_shortest_path_distances(&self) -> BTreeMap<usize,MinMax<i64>> {
+        self.vertex_ids.iter()
+            .zip(self.distance.iter())
+            .map(|(id, distance)| (*id, *distance))
+            .collect()
+    }
+
+    fn find_path(&self, dest_index: usize) -> Vec<usize> {
+        let mut path = vec!(self.vertex_ids[dest_index]);
+        let mut current = dest_index;
+        while let Some(prev) = self.predecessor[current] {
+            path.push(prev);
+            current = self.graph.vertex_index(prev).unwrap();
+        }
+        path.reverse();
+        path
+    }
+
+    /// Returns the paths calculated to each of the vertexes (keyed by dest vertex)
+    pub fn get_shortest_paths(&self) -> BTreeMap<usize, ShortestPathInfo> {
+        let mut result = BTreeMap::<usize,ShortestPathInfo>::new();
+        for (index, id) in self.vertex_ids.iter().enumerate() {
+            if self.distance[index] == MinMax::Max {
+                result.insert(*id, ShortestPathInfo::unreachable(self.starting_vertex, *id));
+                continue;
+            }
+            let path = self.find_path(index);
+            // with several sources, the path starts at the one nearest to the vertex
+            result.insert(*id, ShortestPathInfo {
+                source: path[0],
+                dest: *id,
+                distance: self.distance[index],
+                path_len: path.len(),
+                path,
+                has_negative_cycle: false,
+            });
+        }
+        result
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::dag::DagPaths;
+    use crate::bellman::Bellman;
+    use crate::error::Error;
+    use crate::minmax::MinMax::{Value,Max};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,12),Some(1));
+		assert_eq!(g.add_edge(1,3,-13),Some(2));
+		assert_eq!(g.add_edge(2,3,23),Some(3));
+		assert_eq!(g.add_edge(2,4,-24),Some(4));
+		assert_eq!(g.add_edge(3,4,34),Some(5));
+		assert_eq!(g.add_edge(4,5,-45),Some(6));
+		g.define_vertex(6);
+	}
+
+    #[test]
+    fn topological_sort() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        assert_eq!(g.topological_sort().unwrap(),vec!(1,6,2,3,4,5));
+		assert_eq!((&mut g).add_edge(5,2,1),Some(7));
+        match g.topological_sort() {
+            Err(Error::NotAcyclic(cycle)) => {
+                assert_eq!(cycle.vertexes,vec!(2,4,5));
+                assert_eq!(cycle.edges,vec!(4,6,7));
+            },
+            _ => panic!("cycle not detected"),
+        }
+        assert!(!g.is_acyclic());
+    }
+
+    #[test]
+    fn matches_bellman() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = DagPaths::new(&g);
+        d.calculate_shortest_paths(1).unwrap();
+        let mut b = Bellman::new();
+        b.calculate_shortest_paths(&g,1).unwrap();
+        assert_eq!(d.get_shortest_path_distances(),b.get_shortest_path_distances());
+        assert_eq!(d.get_shortest_paths()[&5].path,vec!(1,2,4,5));
+        assert_eq!(d.get_shortest_paths()[&6].path,vec!(6));
+    }
+
+    #[test]
+    fn longest_paths() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = DagPaths::with_longest_paths(&g);
+        d.calculate_shortest_paths(1).unwrap();
+        assert_eq!(d.get_shortest_path_distance(4),Value(69));
+        assert_eq!(d.get_shortest_path_distance(5),Value(24));
+        assert_eq!(d.get_shortest_path_distance(6),Max);
+        assert_eq!(d.get_shortest_paths()[&5].path,vec!(1,2,3,4,5));
+
+		assert_eq!((&mut g).add_edge(5,1,1),Some(7));
+        let mut d = DagPaths::with_longest_paths(&g);
+        assert!(matches!(d.calculate_shortest_paths(1),Err(Error::NotAcyclic(_))));
+    }
+
+}
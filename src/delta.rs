@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap};
+use std::thread;
+
+use crate::dirgraph::DirectedGraph;
+use crate::error::{Error, Result};
+
+use log::{ info, debug, trace, warn };
+use crate::minmax::{MinMax,MinMax::Value};
+
+// minimum number of vertexes in a phase before their edges are split between threads (fewer
+// aren't worth the cost of starting the threads)
+const PARALLEL_MIN_VERTEXES: usize = 256;
+
+/// Single source shortest paths using delta-stepping.  The vertexes are kept in buckets of
+/// width delta by their tentative distance and the buckets are settled in increasing order.
+/// Edges of weight up to delta (light) can put a vertex back into the current bucket, so they
+/// are relaxed repeatedly until the bucket stays empty, and the heavy edges of the bucket's
+/// vertexes are then relaxed once.  The relaxations for all of the vertexes in a phase are
+/// found in parallel and then applied in order, so the distances are the same as Dijkstra's
+/// for any delta and number of threads.  Edges must not have negative weights
+pub struct DeltaStepping<'a> {
+        graph: &'a DirectedGraph,
+        /// bucket width (0 picks one from the edge weights and degrees of the graph)
+        delta: i64,
+        /// number of threads used to find the relaxations (0 uses the available cores)
+        threads: usize,
+        /// tentative distance of each vertex (by dense index)
+        distance: Vec<MinMax<i64>>,
+}
+
+impl<'a> DeltaStepping<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> DeltaStepping<'a> {
+        DeltaStepping { graph, delta: 0, threads: 1, distance: Vec::new() }
+    }
+
+    /// Creates a search that finds the relaxations on the given number of threads (0 uses the
+    /// number of available cores).  The results are the same as with a single thread
+    pub fn with_threads(graph: &'a DirectedGraph, threads: usize) -> DeltaStepping<'a> {
+        DeltaStepping { graph, delta: 0, threads, distance: Vec::new() }
+    }
+
+    /// Sets the bucket width (0 picks one from the graph).  Small values settle fewer vertexes
+    /// at a time (approaching Dijkstra) while large ones relax edges more often (approaching
+    /// Bellman-Ford)
+    pub fn set_delta(&mut self, delta: i64) {
+        self.delta = delta.max(0);
+    }
+
+    /// Returns the bucket width used for the calculation
+    pub fn delta(&self) -> i64 {
+        if self.delta > 0 {
+            return self.delta;
+        }
+        // the max weight over the average degree keeps the number of times each vertex is
+        // relaxed in a bucket low without too many buckets
+        let max_weight = self.graph.edge_iter().map(|(_id, e)| e.weight()).max().unwrap_or(1);
+        let average_degree = (self.graph.edge_count() / self.graph.vertex_count().max(1)).max(1) as i64;
+        (max_weight / average_degree).max(1)
+    }
+
+    /// Returns the number of threads used to find the relaxations
+    pub fn threads(&self) -> usize {
+        if self.threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        }
+        else {
+            self.threads
+        }
+    }
+
+    /// Calculates the shortest distances from the start vertex to every vertex
+    /// Returns NegativeEdges if the graph has negative edge weights
+    pub fn calculate_shortest_paths(&mut self, start: usize) -> Result<()> {
+        let start_index = self.graph.vertex_index(start).ok_or(Error::UnknownVertex(start))?;
+        let negative_edges = self.graph.get_negative_edge_ids();
+        if !negative_edges.is_empty() {
+            warn!("Graph has {} edges with negative weights",negative_edges.len());
+            return Err(Error::NegativeEdges(negative_edges));
+        }
+        let delta = self.delta();
+        let threads = self.threads();
+        info!("Delta-stepping from {} with delta {} on {} threads",start,delta,threads);
+
+        // light and heavy edges (dest index, weight) of each vertex by dense index
+        let n = self.graph.vertex_count();
+        let mut light = vec![Vec::<(usize,i64)>::new(); n];
+        let mut heavy = vec![Vec::<(usize,i64)>::new(); n];
+        for (_id, e) in self.graph.edge_iter() {
+            if e.weight() <= delta {
+                light[e.source_index()].push((e.dest_index(), e.weight()));
+            }
+            else {
+                heavy[e.source_index()].push((e.dest_index(), e.weight()));
+            }
+        }
+
+        self.distance = vec![MinMax::Max; n];
+        // vertexes by distance / delta (entries are out of date once the vertex has moved to
+        // a lower bucket)
+        let mut buckets = BTreeMap::<i64,Vec<usize>>::new();
+        self.distance[start_index] = Value(0);
+        buckets.insert(0, vec!(start_index));
+
+        let mut phases = 0;
+        // vertexes settled by the current bucket, whose heavy edges are relaxed at the end
+        let mut settled = Vec::<usize>::new();
+        let mut in_settled = vec![false; n];
+        while let Some((bucket, _)) = buckets.first_key_value() {
+            let bucket = *bucket;
+            while let Some(entries) = buckets.remove(&bucket) {
+                let mut current : Vec<usize> = entries.into_iter()
+                    .filter(|v| matches!(self.distance[*v], Value(d) if d / delta == bucket))
+                    .collect();
+                current.sort_unstable();
+                current.dedup();
+                trace!("Bucket {} phase with {} vertexes",bucket,current.len());
+                let requests = find_requests(&current, &light, &self.distance, threads);
+                for v in current {
+                    if !in_settled[v] {
+                        in_settled[v] = true;
+                        settled.push(v);
+                    }
+                }
+                self.relax(requests, &mut buckets, delta);
+                phases += 1;
+            }
+            let requests = find_requests(&settled, &heavy, &self.distance, threads);
+            self.relax(requests, &mut buckets, delta);
+            debug!("Bucket {} settled {} vertexes",bucket,settled.len());
+            for v in settled.drain(..) {
+                in_settled[v] = false;
+            }
+        }
+        info!("Delta-stepping complete after {} phases",phases);
+        Ok(())
+    }
+
+    // applies the relaxation requests, moving the vertexes whose distance is lowered to the
+    // bucket of their new distance
+    fn relax(&mut self, requests: Vec<(usize,i64)>, buckets: &mut BTreeMap<i64,Vec<usize>>, delta: i64) {
+        for (v, distance) in requests {
+            if Value(distance) < self.distance[v] {
+                self.distance[v] = Value(distance);
+                buckets.entry(distance / delta).or_default().push(v);
+            }
+        }
+    }
+
+    /// Returns the distance calculated to the vertex (Max if it can't be reached)
+    /// Returns NA if the vertex is not in the graph or the paths haven't been calculated
+    pub fn get_shortest_path_distance(&self, vertex: usize) -> MinMax<i64> {
+        self.graph.vertex_index(vertex)
+            .and_then(|index| self.distance.get(index).cloned())
+            .unwrap_or(MinMax::NA)
+    }
+
+    /// Returns the distance to every vertex of the graph (Max for the ones that can't be
+    /// reached), in the same form as Dijkstra::get_shortest_path_distances
+    pub fn get_shortest_path_distances(&self) -> BTreeMap<usize, MinMax<i64>> {
+        self.graph.vertex_ids_by_index().iter()
+            .map(|id| (*id, self.get_shortest_path_distance(*id)))
+            .collect()
+    }
+}
+
+// finds the distances the edges of the vertexes would give their destinations, splitting the
+// vertexes between threads when there are enough of them.  The requests are returned in the
+// same order as from a single thread
+fn find_requests(vertexes: &[usize], edges: &[Vec<(usize,i64)>], distance: &[MinMax<i64>], threads: usize) -> Vec<(usize,i64)> {
+    let requests_from = |vertexes: &[usize]| -> Vec<(usize,i64)> {
+        vertexes.iter()
+            .filter_map(|v| match distance[*v] { Value(d) => Some((*v, d)), _ => None })
+            .flat_map(|(v, d)| edges[v].iter().map(move |(dest, weight)| (*dest, d + weight)))
+            .filter(|(dest, new_distance)| Value(*new_distance) < distance[*dest])
+            .collect()
+    };
+    if threads <= 1 || vertexes.len() < PARALLEL_MIN_VERTEXES {
+        return requests_from(vertexes);
+    }
+    // each thread takes a consecutive chunk of the vertexes so the results can be joined in order
+    let chunk_size = vertexes.len().div_ceil(threads);
+    thread::scope(|scope| {
+        let workers : Vec<_> = vertexes.chunks(chunk_size).map(|chunk| {
+            let requests_from = &requests_from;
+            scope.spawn(move || requests_from(chunk))
+        }).collect();
+        workers.into_iter()
+            .flat_map(|worker| worker.join().expect("Delta-stepping worker thread panicked"))
+            .collect()
+    })
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::dijkstra::Dijkstra;
+    use crate::delta::DeltaStepping;
+    use crate::error::Error;
+    use crate::minmax::MinMax::{Value,Max,NA};
+
+    #[test]
+    fn matches_dijkstra() {
+        // 30x30 grid with edges both ways, including 0 weights and weights above each delta
+        let mut g = DirectedGraph::new();
+        let id = |row: usize, col: usize| row * 30 + col + 1;
+        for row in 0..30 {
+            for col in 0..30 {
+                let weight = ((row * 7 + col * 13) % 20) as i64;
+                if col + 1 < 30 {
+                    (&mut g).add_edge(id(row,col), id(row,col+1), weight);
+                    (&mut g).add_edge(id(row,col+1), id(row,col), 20 - weight);
+                }
+                if row + 1 < 30 {
+                    (&mut g).add_edge(id(row,col), id(row+1,col), weight + 3);
+                    (&mut g).add_edge(id(row+1,col), id(row,col), weight / 2);
+                }
+            }
+        }
+        let mut d = Dijkstra::new(1);
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        d.calculate_shortest_paths(&g, 1).unwrap();
+        let expected = d.get_shortest_path_distances();
+
+        for (delta, threads) in [(0, 1), (1, 1), (5, 4), (50, 3), (1000, 0)] {
+            let mut delta_stepping = DeltaStepping::with_threads(&g, threads);
+            delta_stepping.set_delta(delta);
+            delta_stepping.calculate_shortest_paths(1).unwrap();
+            assert_eq!(delta_stepping.get_shortest_path_distances(), expected, "delta {} threads {}", delta, threads);
+        }
+    }
+
+    #[test]
+    fn unreachable() {
+        // 4 only has an edge out and 5 is in a separate part of the graph
+        let mut g = DirectedGraph::new();
+        for (source, dest, weight) in [(1,2,3),(2,3,4),(4,1,1),(5,6,2)] {
+            (&mut g).add_edge(source,dest,weight);
+        }
+        let mut delta_stepping = DeltaStepping::with_threads(&g, 2);
+        assert_eq!(delta_stepping.get_shortest_path_distance(1),NA);
+        delta_stepping.set_delta(2);
+        delta_stepping.calculate_shortest_paths(1).unwrap();
+        let distances = delta_stepping.get_shortest_path_distances();
+        assert_eq!(distances.len(),6);
+        assert_eq!((distances[&3],distances[&4],distances[&5],distances[&6]),(Value(7),Max,Max,Max));
+        assert_eq!(delta_stepping.get_shortest_path_distance(9),NA);
+
+        // starting again from the other part replaces the earlier distances
+        delta_stepping.calculate_shortest_paths(5).unwrap();
+        assert_eq!((delta_stepping.get_shortest_path_distance(6),delta_stepping.get_shortest_path_distance(3)),(Value(2),Max));
+    }
+
+    #[test]
+    fn negative_edges() {
+        let mut g = DirectedGraph::new();
+        (&mut g).add_edge(1, 2, 4);
+        (&mut g).add_edge(2, 3, -1);
+        let mut delta_stepping = DeltaStepping::new(&g);
+        assert!(matches!(delta_stepping.calculate_shortest_paths(1), Err(Error::NegativeEdges(ids)) if ids == vec!(2)));
+        assert!(matches!(delta_stepping.calculate_shortest_paths(9), Err(Error::UnknownVertex(9))));
+    }
+
+}
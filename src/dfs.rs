@@ -1,175 +1,237 @@
-static mut max_out_level : u32= 0;
-static mut max_in_level : u32 = 0;
-
-struct DFS {
-    graph:  DirectedGraph,
-	explored:  HashMap::<u32,bool>,
-	pub finished_order:  Vec::<u32>,
-	pub start_search:  HashMap::<u32,Vec::<u32>>,
-	top_search_cnts:  HashMap::<u32, usize>,
-
+use std::collections::{BTreeMap};
+
+use crate::dirgraph::DirectedGraph;
+use crate::graphbuilder::GraphBuilder;
+
+use log::{ info, debug, trace };
+use crate::error::{Error, Result};
+
+/// Strongly connected components of a graph along with the condensation of the graph, which
+/// has a vertex for each component and is always acyclic
+#[derive(Debug,Clone)]
+pub struct StronglyConnectedComponents {
+    /// vertexes of each component (in vertex id order), largest component first
+    pub components: Vec<Vec<usize>>,
+    /// position in components of the component of each vertex
+    pub component_of: BTreeMap<usize,usize>,
+    /// graph with a vertex for each component (the vertex id is its position in components)
+    /// and the lowest weight edge between each pair of connected components
+    pub condensation: DirectedGraph,
 }
 
-impl DFS {
-
-	pub fn new() -> DFS {
-		DFS {
-				explored:  HashMap::<u32,bool>::new(),
-				finished_order:  Vec::<u32>::new(),
-				start_search : HashMap::<u32,Vec::<u32>>::new(),
-				top_search_cnts : HashMap::<u32,usize>::new(),
-		}
-	}
-	pub fn add_search_entry(&mut self, vertex: u32, count: usize) {
-
-			self.top_search_cnts.insert(vertex,count);
-			let mut removed = None;
-			if self.top_search_cnts.len() > 10 {
-				let top_search_iter = self.top_search_cnts.iter();
-				let mut top_search_count_vec : Vec::<(u32, usize)> = top_search_iter.map(|(k,v)| (*k, *v)).collect();
-				top_search_count_vec.sort_by(|a, b| b.1.cmp(&a.1));
-				removed = top_search_count_vec.pop();
-			}
-			if let Some(entry) = removed {
-				self.top_search_cnts.remove(&entry.0);
-				
-			}
-			
-	}
-
-	pub fn dfs_outgoing(&mut self, vertex_id:  u32, start_vertex: u32, level: u32) {
-			
-//			let spacer = (0..level*5).map(|_| " ").collect::<String>();
-			unsafe {
-			if level > max_out_level {
-				max_out_level = level;
-//					println!("reached level {}", max_out_level);
-			}
-			}
-			
-			// Set current node to explored
-			self.explored.insert(vertex_id,true);
-
-			let mut cur_len : usize = 0;
-		
-			{
-				let group_list = self.start_search.entry(start_vertex).or_insert(Vec::<u32>::new());
-				group_list.push(vertex_id);
-				cur_len = group_list.len();
-			}
-			self.add_search_entry(start_vertex,cur_len);
-
-			
-			let next_v : Vertex;
-
-			if let Some(vertex) = self.vertex_map.get(&vertex_id) {
-
-				next_v = vertex.clone();
-			}
-
-			else {
-				panic!("invalid vertex");
-			}
-
-			// Search through each edge
-			for edge in next_v.outgoing.keys() {
-				let next_vertex = edge.clone();
-				if !self.explored.contains_key(&edge) {
-					self.dfs_outgoing(next_vertex,start_vertex,level+1);
-				}
-				else {
-			//		println!("{}Vertex {} is already explored",spacer,edge);
-				}
-			}
-			// so add it to the finished list
-			self.finished_order.push(vertex_id);
-	}
+/// Depth first searches of a graph, on either the outgoing or the incoming edges, as used by
+/// Kosaraju's algorithm for the strongly connected components.  The searches are iterative
+/// (with an explicit stack) so deep graphs don't overflow the call stack
+pub struct DFS<'a> {
+    graph: &'a DirectedGraph,
+    /// vertexes already reached by a search (by dense index)
+    explored: Vec<bool>,
+    /// vertexes in the order their searches finished
+    pub finished_order: Vec<usize>,
+    /// vertexes reached by the search started from each vertex of the loop
+    pub start_search: BTreeMap<usize,Vec<usize>>,
+    /// deepest level reached by the searches of the last loop
+    max_level: usize,
+}
 
-	pub fn dfs_incoming(&mut self, vertex_id:  u32, start_vertex: u32, level: u32) {
-			
-//			let spacer = (0..level*5).map(|_| " ").collect::<String>();
-			unsafe {
-			if level > max_in_level {
-				max_in_level = level;
-//				println!("reached level {}", max_in_level);
-			}
-			}
-			
-			// Set current node to explored
-			self.explored.insert(vertex_id,true);
-
-			let group_list = self.start_search.entry(start_vertex).or_insert(Vec::<u32>::new());
-			group_list.push(vertex_id);
-			let cur_len = group_list.len();
-			self.add_search_entry(start_vertex,cur_len);
-
-			let next_v : Vertex;
-
-			if let Some(vertex) = self.vertex_map.get(&vertex_id) {
-
-				next_v = vertex.clone();
-			}
-
-			else {
-				panic!("invalid vertex");
-			}
-
-			// Search through each edge
-			for edge in next_v.incoming.keys() {
-				let next_vertex = edge.clone();
-				if !self.explored.contains_key(&edge) {
-					self.dfs_incoming(next_vertex,start_vertex,level+1);
-				}
-				else {
-			//		println!("{}Vertex {} is already explored",spacer,edge);
-				}
-			}
-			// so add it to the finished list
-			self.finished_order.push(vertex_id);
-	}
+impl<'a> DFS<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> DFS<'a> {
+        DFS {
+            graph,
+            explored: Vec::<bool>::new(),
+            finished_order: Vec::<usize>::new(),
+            start_search: BTreeMap::<usize,Vec<usize>>::new(),
+            max_level: 0,
+        }
+    }
+
+    // clears the results of the last loop
+    fn reset(&mut self) {
+        self.explored = vec![false; self.graph.vertex_count()];
+        self.finished_order.clear();
+        self.start_search.clear();
+        self.max_level = 0;
+    }
+
+    // searches from start_vertex on the outgoing (or incoming) edges, skipping vertexes already
+    // explored, adding the vertexes reached to the start vertex's search
+    fn dfs(&mut self, start_vertex: usize, outgoing: bool) -> Result<()> {
+        let neighbors = |vertex: usize| if outgoing { self.graph.get_outgoing_vertex_ids(vertex) } else { self.graph.get_incoming_vertex_ids(vertex) };
+
+        let mut group_list = vec!(start_vertex);
+        self.explored[self.graph.vertex_index(start_vertex).unwrap()] = true;
+        // each entry is a vertex being searched with its neighbors and the next one to check
+        let mut stack = vec!((start_vertex, neighbors(start_vertex)?, 0));
+        let mut level = 0;
+        while let Some((vertex, next_vertexes, pos)) = stack.last_mut() {
+            if *pos < next_vertexes.len() {
+                let next_vertex = next_vertexes[*pos];
+                *pos += 1;
+                let index = self.graph.vertex_index(next_vertex).unwrap();
+                if !self.explored[index] {
+                    level += 1;
+                    trace!("Vertex {} reached from {} at level {}",next_vertex,vertex,level);
+                    self.explored[index] = true;
+                    group_list.push(next_vertex);
+                    stack.push((next_vertex, neighbors(next_vertex)?, 0));
+                    self.max_level = self.max_level.max(level);
+                }
+            }
+            else {
+                // all of the vertex's neighbors are explored so add it to the finished list
+                self.finished_order.push(*vertex);
+                stack.pop();
+                level = level.saturating_sub(1);
+            }
+        }
+        self.start_search.insert(start_vertex, group_list);
+        Ok(())
+    }
+
+    /// Searches on the incoming edges from each vertex of the list not reached by an earlier search
+    pub fn dfs_loop_incoming(&mut self, list: &[usize]) -> Result<()> {
+        self.dfs_loop(list, false)
+    }
+
+    /// Searches on the outgoing edges from each vertex of the list not reached by an earlier search
+    pub fn dfs_loop_outgoing(&mut self, list: &[usize]) -> Result<()> {
+        self.dfs_loop(list, true)
+    }
+
+    fn dfs_loop(&mut self, list: &[usize], outgoing: bool) -> Result<()> {
+        self.reset();
+        for vertex in list {
+            match self.graph.vertex_index(*vertex) {
+                Some(index) if self.explored[index] => (),
+                Some(_) => self.dfs(*vertex, outgoing)?,
+                None => return Err(Error::UnknownVertex(*vertex)),
+            }
+        }
+        debug!("{} searches reached a maximum level of {}",self.start_search.len(),self.max_level);
+        Ok(())
+    }
+
+    /// Deepest level reached by the searches of the last loop
+    pub fn max_level(&self) -> usize {
+        self.max_level
+    }
+
+    /// Returns the start vertex and number of vertexes reached of the n largest searches of the
+    /// last loop (largest first)
+    pub fn top_search_cnts(&self, n: usize) -> Vec<(usize,usize)> {
+        let mut counts : Vec<(usize,usize)> = self.start_search.iter()
+            .map(|(start, group_list)| (*start, group_list.len()))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Finds the strongly connected components using Kosaraju's algorithm -- a loop of searches
+    /// on the incoming edges gives the finishing order, then searching on the outgoing edges in
+    /// reverse finishing order reaches exactly one component with each search
+    pub fn strongly_connected_components(&mut self) -> Result<StronglyConnectedComponents> {
+        info!("Finding strongly connected components");
+        self.dfs_loop_incoming(&self.graph.get_vertex_ids())?;
+        let order : Vec<usize> = self.finished_order.iter().rev().cloned().collect();
+        self.dfs_loop_outgoing(&order)?;
+
+        let mut components : Vec<Vec<usize>> = self.start_search.values()
+            .map(|group_list| {
+                let mut component = group_list.clone();
+                component.sort_unstable();
+                component
+            })
+            .collect();
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        info!("Found {} strongly connected components",components.len());
+
+        let mut component_of = BTreeMap::<usize,usize>::new();
+        for (pos, component) in components.iter().enumerate() {
+            for vertex in component {
+                component_of.insert(*vertex, pos);
+            }
+        }
+
+        // keep only the lowest weight edge between each pair of components
+        let mut lowest_edges = BTreeMap::<(usize,usize),i64>::new();
+        for (_id, edge) in self.graph.edge_iter() {
+            let (source, dest) = (component_of[&edge.source()], component_of[&edge.dest()]);
+            if source != dest {
+                let weight = lowest_edges.entry((source, dest)).or_insert(edge.weight());
+                *weight = (*weight).min(edge.weight());
+            }
+        }
+        let mut condensation = DirectedGraph::new();
+        for pos in 0..components.len() {
+            condensation.define_vertex(pos);
+        }
+        for ((source, dest), weight) in lowest_edges {
+            (&mut condensation).add_edge(source, dest, weight);
+        }
+
+        Ok(StronglyConnectedComponents { components, component_of, condensation })
+    }
+}
 
-	pub fn dfs_loop_incoming(&mut self, list: &Vec<u32>) {
-
-//		println!("Looping on incoming DFS");
-		self.finished_order = Vec::<u32>::new();
-		self.start_search = HashMap::<u32,Vec::<u32>>::new();
-		self.explored = HashMap::<u32,bool>::new();
-		self.top_search_cnts = HashMap::<u32,usize>::new();
-
-		let mut _count : usize = 0;
-		for v in list {
-/*			if _count % 1000000 == 0 {
-				print!("*");
-				io::stdout().flush().unwrap();
-			} */
-			let vertex = v.clone();
-//			println!("Looping on {}",vertex);
-			if !self.explored.contains_key(&vertex) {
-				self.dfs_incoming(vertex,vertex,0);
-			}
-			_count += 1;
-		}
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::dfs::DFS;
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+    // three components {1,2,3}, {4,5} and {6} connected 1,2,3 -> 4,5 -> 6
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,1),Some(1));
+		assert_eq!(g.add_edge(2,3,1),Some(2));
+		assert_eq!(g.add_edge(3,1,1),Some(3));
+		assert_eq!(g.add_edge(3,4,7),Some(4));
+		assert_eq!(g.add_edge(2,5,3),Some(5));
+		assert_eq!(g.add_edge(4,5,1),Some(6));
+		assert_eq!(g.add_edge(5,4,1),Some(7));
+		assert_eq!(g.add_edge(5,6,2),Some(8));
 	}
 
-	pub fn dfs_loop_outgoing(&mut self, list: &Vec<u32>) {
-//		println!("Looping on outgoing DFS");
-		self.finished_order = Vec::<u32>::new();
-		self.start_search = HashMap::<u32,Vec::<u32>>::new();
-		self.explored = HashMap::<u32,bool>::new();
-		self.top_search_cnts = HashMap::<u32,usize>::new();
-
-		let mut _count : usize = 0;
-		for v in list {
-/*			if _count % 1000000 == 0 {
-				print!("#");
-				io::stdout().flush().unwrap();
-			} */
-			let vertex = v.clone();
-//			println!("Looping on {}",vertex);
-			if !self.explored.contains_key(&vertex) {
-				self.dfs_outgoing(vertex,vertex,0);
-			}
-		}
-	}
+    #[test]
+    fn components() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut dfs = DFS::new(&g);
+        let scc = dfs.strongly_connected_components().unwrap();
+        assert_eq!(scc.components,vec!(vec!(1,2,3),vec!(4,5),vec!(6)));
+        assert_eq!(scc.component_of[&5],1);
+        assert_eq!(scc.condensation.vertex_count(),3);
+        assert_eq!(scc.condensation.edge_count(),2);
+        assert_eq!(scc.condensation.get_outgoing_connection_weight(0,1).unwrap(),Some(3));
+        assert!(scc.condensation.is_acyclic());
+        let sizes : Vec<usize> = dfs.top_search_cnts(2).iter().map(|(_start, count)| *count).collect();
+        assert_eq!(sizes,vec!(3,2));
+    }
+
+    #[test]
+    fn deep_graph() {
+        init();
+		let mut g = DirectedGraph::new();
+        // a long cycle would overflow the stack with a recursive search
+        let n = 50_000;
+        for v in 1..n {
+            (&mut g).add_edge(v,v+1,1);
+        }
+        (&mut g).add_edge(n,1,1);
+        let mut dfs = DFS::new(&g);
+        let scc = dfs.strongly_connected_components().unwrap();
+        assert_eq!(scc.components.len(),1);
+        assert_eq!(scc.components[0].len(),n);
+        assert_eq!(dfs.max_level(),n-1);
+    }
 
 }
@@ -1,5 +1,5 @@
 extern crate minheap;
-use std::collections::{BTreeMap};
+use std::collections::{BTreeMap, BTreeSet};
 use minheap::MinHeap;
 
 use crate::dirgraph::DirectedGraph;
@@ -7,6 +7,8 @@ use crate::dirgraph::DirectedGraph;
 use log::{ /* info ,*/ error, debug, warn, trace };
 use crate::minmax::{MinMax,MinMax::Value,MinMax::NA};
 use crate::shortpathinfo::ShortestPathInfo;
+use crate::queue::VertexQueue;
+use crate::error::{Error, Result};
 
 #[derive(Debug,Clone,PartialOrd,PartialEq)]
 pub struct VertexInfo {
@@ -15,74 +17,204 @@ pub struct VertexInfo {
     preceeding_vertex: Option<usize>,
 }
 
+// the original queue, which orders equal distances by the preceeding vertex
+impl VertexQueue for MinHeap<VertexInfo> {
+    fn insert(&mut self, vertex: usize, distance: MinMax<i64>) {
+        MinHeap::insert(self, vertex, VertexInfo { score: distance, preceeding_vertex: None });
+    }
+
+    fn decrease(&mut self, vertex: usize, distance: MinMax<i64>, predecessor: Option<usize>) {
+        let index = *self.get_id_index(vertex).unwrap();
+        self.update(index, VertexInfo { score: distance, preceeding_vertex: predecessor });
+    }
+
+    fn distance(&self, vertex: usize) -> Option<MinMax<i64>> {
+        self.peek_id_data(vertex).map(|info| info.score)
+    }
+
+    fn pop_min(&mut self) -> Option<(usize, MinMax<i64>)> {
+        self.get_min_entry().map(|(vertex, info)| (vertex, info.score))
+    }
+}
+
 
-pub struct Dijkstra {
+pub struct Dijkstra<Q: VertexQueue = MinHeap<VertexInfo>> {
         /// Starting vertex for the algoritm
         starting_vertex:  usize,
-        ///  Unprocessed vertex -- Min Heap based the greedy score for each vertex
-        ///  initially set to a maximum value, and is reduced during processing
-        unprocessed_vertex : MinHeap::<VertexInfo>,
+        ///  Unprocessed vertex -- priority queue of the greedy score for each vertex (by dense
+        ///  index) initially set to a maximum value, and is reduced during processing
+        unprocessed_vertex : Q,
+        /// vertexes initialized since the last calculation, which are added to the queue once
+        /// the graph is known to find their index
+        initialized_vertex : Vec<usize>,
         /// Processed Vertexes -- Map of all vertexes already processed, along with there distance
         /// from the starting vertex
         processed_vertex : BTreeMap::<usize,VertexInfo>,
         /// If set, vertex X  contains the preceeding vertex in the path from the starting vertex
         /// used to build the path from Start to this vertex
         predecessor:  BTreeMap<usize,Option<usize>>,
+        /// Targets of the last calculation which could not be reached from the starting vertex
+        unreached_targets: Vec<usize>,
+        /// skips checking the graph for negative edge weights (which give incorrect results)
+        allow_negative: bool,
+        /// edges and vertexes temporarily left out of the paths (without changing the graph)
+        excluded_edges: BTreeSet<usize>,
+        excluded_vertexes: BTreeSet<usize>,
 }
 
 
 impl Dijkstra {
 
     pub fn new(starting_vertex: usize) -> Dijkstra {
+        Dijkstra::with_queue(starting_vertex, MinHeap::<VertexInfo>::new())
+    }
+
+    /// Creates a Dijkstra instance that doesn't reject graphs with negative edge weights, for
+    /// use when the caller knows the paths will still be correct (e.g. reweighted graphs)
+    pub fn with_allow_negative(starting_vertex: usize) -> Dijkstra {
+        let mut dijkstra = Dijkstra::new(starting_vertex);
+        dijkstra.allow_negative = true;
+        dijkstra
+    }
+}
+
+impl<Q: VertexQueue> Dijkstra<Q> {
+
+    /// Creates a Dijkstra instance using the given (empty) queue for the unprocessed vertexes
+    pub fn with_queue(starting_vertex: usize, queue: Q) -> Dijkstra<Q> {
         Dijkstra { 
             starting_vertex:  starting_vertex,
-            unprocessed_vertex : MinHeap::<VertexInfo>::new(),
+            unprocessed_vertex : queue,
+            initialized_vertex : Vec::<usize>::new(),
             processed_vertex : BTreeMap::<usize,VertexInfo>::new(),
-            predecessor : BTreeMap::<usize,Option<usize>>::new()
+            predecessor : BTreeMap::<usize,Option<usize>>::new(),
+            unreached_targets : Vec::<usize>::new(),
+            allow_negative : false,
+            excluded_edges : BTreeSet::<usize>::new(),
+            excluded_vertexes : BTreeSet::<usize>::new(),
         }
 
     }
 
+    /// Sets whether graphs with negative edge weights are accepted (see with_allow_negative)
+    pub fn set_allow_negative(&mut self, allow_negative: bool) {
+        self.allow_negative = allow_negative;
+    }
+
+    /// Excludes the edges and vertexes from the paths found by the next calculation, as if
+    /// they had been removed from the graph
+    pub fn exclude(&mut self, edges: BTreeSet<usize>, vertexes: BTreeSet<usize>) {
+        self.excluded_edges = edges;
+        self.excluded_vertexes = vertexes;
+    }
+
     pub fn initialize_vertex(&mut self, vertex_id: usize) {
-        self.unprocessed_vertex.insert(vertex_id,VertexInfo {  score: MinMax::Max, preceeding_vertex: None} );
+        self.initialized_vertex.push(vertex_id);
         self.predecessor.insert(vertex_id,None);
     }
         
 
-    pub fn calculate_shortest_paths(&mut self, graph: &DirectedGraph, starting_vertex: usize) {
-        trace!("Starting shortest path calucation for Vertex {}",starting_vertex);
+    pub fn calculate_shortest_paths(&mut self, graph: &DirectedGraph, starting_vertex: usize) -> Result<()> {
+        self.calculate_shortest_paths_to(graph, starting_vertex, &[])
+    }
 
-        if let Some(starting_index) = self.unprocessed_vertex.get_id_index(starting_vertex) {
+    /// Find the shortest paths from the starting vertex, stopping as soon as the paths to all
+    /// of the targets are known (all vertexes are processed if targets is empty).  Targets that
+    /// can't be reached are available from unreached_targets afterwards.  Calling it again
+    /// continues the search, with targets that were already processed resolved immediately
+    /// Returns NegativeEdges if the graph has negative edge weights (unless allowed)
+    pub fn calculate_shortest_paths_to(&mut self, graph: &DirectedGraph, starting_vertex: usize, targets: &[usize]) -> Result<()> {
+        self.calculate_shortest_paths_multi(graph, &[(starting_vertex,0)], targets)
+    }
 
-            let index = starting_index.clone();
-            self.unprocessed_vertex.delete(index);
-            
-            // setup the initial distance for the starting vertex to 0 (to itself) and no
-            // associated Vertex
-            self.processed_vertex.insert(starting_vertex,VertexInfo { score: Value(0), preceeding_vertex: None } );
-            self.predecessor.insert(starting_vertex,None);
+    /// Find the shortest paths from the nearest of several sources, each given with an initial
+    /// offset added to the distance of paths starting from it.  The source of each path is the
+    /// one it originates from.  Targets are handled as in calculate_shortest_paths_to
+    /// Returns NegativeEdges if the graph has negative edge weights (unless allowed)
+    pub fn calculate_shortest_paths_multi(&mut self, graph: &DirectedGraph, sources: &[(usize,i64)], targets: &[usize]) -> Result<()> {
+        trace!("Starting shortest path calucation for Vertexes {:?}",sources);
+
+        if !self.allow_negative {
+            let negative_edges = graph.get_negative_edge_ids();
+            if !negative_edges.is_empty() {
+                warn!("Graph has {} edges with negative weights",negative_edges.len());
+                return Err(Error::NegativeEdges(negative_edges));
+            }
+        }
 
-            self.update_scoring(graph, starting_vertex);
+        for vertex in std::mem::take(&mut self.initialized_vertex) {
+            let index = graph.vertex_index(vertex).ok_or(Error::UnknownVertex(vertex))?;
+            self.unprocessed_vertex.insert(index,MinMax::Max);
+        }
 
-            while let Some((next_vertex,next_vertex_info)) = self.unprocessed_vertex.get_min_entry() {
-                debug!("Processing vertex {} score: {}",next_vertex,next_vertex_info.score);
-                self.processed_vertex.insert(next_vertex,next_vertex_info);
-                self.update_scoring(graph, next_vertex);
+        // targets already processed by an earlier calculation are resolved without searching
+        self.unreached_targets.clear();
+        let mut remaining_targets = BTreeSet::<usize>::new();
+        for target in targets {
+            if let Some(info) = self.processed_vertex.get(target) {
+                if info.score == MinMax::Max {
+                    self.unreached_targets.push(*target);
+                }
+            }
+            else if graph.vertex_index(*target).and_then(|index| self.unprocessed_vertex.distance(index)).is_none() {
+                warn!("Target vertex {} is not in the graph",target);
+                return Err(Error::UnknownVertex(*target));
+            }
+            else {
+                remaining_targets.insert(*target);
             }
-         }       
-        else {
-            warn!("Starting vertex {} is not in the graph",starting_vertex);
         }
 
+        // setup the initial distance for each source to its offset with no associated vertex,
+        // so that the sources are processed first (in order of their offsets)
+        for (source, offset) in sources {
+            let index = graph.vertex_index(*source);
+            let score = match index.and_then(|index| self.unprocessed_vertex.distance(index)) {
+                Some(score) => score,
+                // already processed, so the search continues from where it stopped
+                None if self.processed_vertex.contains_key(source) => continue,
+                None => {
+                    warn!("Starting vertex {} is not in the graph",source);
+                    return Err(Error::UnknownVertex(*source));
+                }
+            };
+            if Value(*offset) < score {
+                self.unprocessed_vertex.decrease(index.unwrap(),Value(*offset),None);
+                self.predecessor.insert(*source,None);
+            }
+        }
+        if let Some((first, _offset)) = sources.first() {
+            self.starting_vertex = *first;
+        }
+
+        let all_vertexes = targets.is_empty();
+        while all_vertexes || !remaining_targets.is_empty() {
+            let (next_vertex,score) = match self.unprocessed_vertex.pop_min() {
+                Some((index, score)) => (graph.vertex_id(index).unwrap(), score),
+                None => break,
+            };
+            let next_vertex_info = VertexInfo { score, preceeding_vertex: self.predecessor[&next_vertex] };
+            debug!("Processing vertex {} score: {}",next_vertex,next_vertex_info.score);
+            if remaining_targets.remove(&next_vertex) && next_vertex_info.score == MinMax::Max {
+                self.unreached_targets.push(next_vertex);
+            }
+            self.processed_vertex.insert(next_vertex,next_vertex_info);
+            self.update_scoring(graph, next_vertex)?;
+        }
+        if !all_vertexes {
+            debug!("All targets processed after processing {} vertexes",self.processed_vertex.len());
+        }
+        Ok(())
+
     }
 
     // Update scoring in the unprocessed pool of vertexes related to 
     // vertex of id.
-    fn update_scoring(&mut self, graph: &DirectedGraph, cur_vertex: usize) {
+    fn update_scoring(&mut self, graph: &DirectedGraph, cur_vertex: usize) -> Result<()> {
         debug!("Dijsktra scoring for vertex {}",cur_vertex);
 
         // get the list of edge that are outgoing from the current vertex
-        let adj_edges = graph.get_outgoing_edges(cur_vertex);
+        let adj_edges = graph.get_outgoing_edges(cur_vertex)?;
         
         // get the distance/score of the current vertex as a start
         let cur_vertex_info = self.processed_vertex.get(&cur_vertex).unwrap().clone();
@@ -91,17 +223,17 @@ impl Dijkstra {
         // update each of this nodes adjancent vertexes, if the new distance
         // is < the current distance
         for e in adj_edges {
+            if self.excluded_edges.contains(&e.id()) || self.excluded_vertexes.contains(&e.dest()) {
+                continue;
+            }
             debug!("Dijsktra updating adjacent {:?}",e);
             // if the adjacent vertex is still in the unprocessed list, then 
             // update the scoring, otherwise skip it (since its already in the processed list)
-            if let Some(cur_info) = self.unprocessed_vertex.peek_id_data(e.dest()) {
+            if let Some(cur_score) = self.unprocessed_vertex.distance(e.dest_index()) {
                 let new_score = cur_vertex_distance + Value(e.weight());
-                if new_score < cur_info.score {
-                    trace!("Update scoring on {} from {} to {}, cur_vertex is {} e.source {}",e.dest(),cur_info.score,new_score,cur_vertex, e.source());
-                    // get the index of the item id
-                    let vertex_index = self.unprocessed_vertex.get_id_index(e.dest()).unwrap().clone();
-                    // and update its value
-                    self.unprocessed_vertex.update(vertex_index,VertexInfo { score: new_score, preceeding_vertex: Some(cur_vertex)} );
+                if new_score < cur_score {
+                    trace!("Update scoring on {} from {} to {}, cur_vertex is {} e.source {}",e.dest(),cur_score,new_score,cur_vertex, e.source());
+                    self.unprocessed_vertex.decrease(e.dest_index(),new_score,Some(cur_vertex));
                     self.predecessor.insert(e.dest(),Some(cur_vertex));
                     trace!("Unprocessed: {:?}",self.unprocessed_vertex);
                     trace!("Predecessors: {:?}",self.predecessor);
@@ -109,9 +241,15 @@ impl Dijkstra {
              }       
             
         }
+        Ok(())
 
     }
 
+    /// Returns the targets of the last calculation which could not be reached
+    pub fn unreached_targets(&self) -> &[usize] {
+        &self.unreached_targets
+    }
+
     pub fn get_processed(&self,index : &usize) -> &VertexInfo {
         &self.processed_vertex[index]
     }
@@ -134,8 +272,6 @@ impl Dijkstra {
     pub fn get_shortest_path_distances(&self) -> BTreeMap<usize, MinMax<i64>> {
 
         let mut result_list = BTreeMap::<usize,MinMax<i64>>::new();
-        // add vertex 0 since it not define  (TODO -- cleanup vertex numbering and naming)
-        result_list.insert(0,MinMax::Max);
         for (v, result) in self.processed_vertex.iter() {
             trace!("getsp_dist: v {} result {:?}",v,result);
             result_list.insert(*v,result.score.clone());
@@ -158,7 +294,7 @@ impl Dijkstra {
         
         // unless this vertex doesn't have a predecessor (indicating no path from starting vertex)
         // add it to the end of the path
-        if self.predecessor[&dest_vertex] != None || dest_vertex == self.starting_vertex {
+        if self.predecessor[&dest_vertex].is_some() || self.processed_vertex.get(&dest_vertex).is_some_and(|info| info.score != MinMax::Max) {
 
             let mut current_vertex = dest_vertex;
             while self.predecessor[&current_vertex] != None  {
@@ -187,9 +323,11 @@ impl Dijkstra {
 
             let path = self.find_path(*v);
             let path_len = path.len();
+            // with several sources, the path starts at the one nearest to the vertex
+            let source = if info.score == MinMax::Max { self.starting_vertex } else { path[0] };
 
             let entry = ShortestPathInfo {
-                source: self.starting_vertex,
+                source,
                 dest: *v,
                 distance: info.score.clone(),
                 path,
@@ -237,3 +375,156 @@ impl Dijkstra {
     }
 }
 
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::dijkstra::Dijkstra;
+    use crate::queue::{VertexQueue, LazyBinaryHeap, DialQueue, RadixHeap};
+    use crate::error::Error;
+    use crate::minmax::MinMax::{Value,Max,NA};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,7),Some(1));
+		assert_eq!(g.add_edge(1,3,9),Some(2));
+		assert_eq!(g.add_edge(2,4,15),Some(3));
+		assert_eq!(g.add_edge(3,4,11),Some(4));
+		assert_eq!(g.add_edge(4,5,6),Some(5));
+		g.define_vertex(6);
+	}
+
+	fn new_dijkstra(g :&DirectedGraph) -> Dijkstra {
+        let mut d = Dijkstra::new(1);
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        d
+	}
+
+    #[test]
+    fn basic() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths(&g, 1).unwrap();
+        assert_eq!(d.get_shortest_path_distance(5),Value(26));
+        assert_eq!(d.get_shortest_path_distance(6),Max);
+        assert_eq!(d.get_shortest_path_distances().len(),6);
+        assert!(d.unreached_targets().is_empty());
+    }
+
+    #[test]
+    fn early_termination() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths_to(&g, 1, &[3]).unwrap();
+        assert_eq!(d.get_shortest_path_distance(3),Value(9));
+        assert_eq!(d.get_shortest_path_distance(5),NA);
+        assert_eq!(d.get_shortest_path_distances().len(),3);
+
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths_to(&g, 1, &[6,4]).unwrap();
+        assert_eq!(d.get_shortest_path_distance(4),Value(20));
+        assert_eq!(d.unreached_targets(),&[6]);
+
+        let mut d = new_dijkstra(&g);
+        assert!(matches!(d.calculate_shortest_paths_to(&g, 1, &[9]),Err(Error::UnknownVertex(9))));
+    }
+
+    #[test]
+    fn repeated_targets() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths_to(&g, 1, &[3]).unwrap();
+        assert_eq!(d.get_shortest_path_distances().len(),3);
+
+        // 2 was processed by the first call, so only 5 is searched for
+        d.calculate_shortest_paths_to(&g, 1, &[2,5]).unwrap();
+        assert_eq!(d.get_shortest_path_distance(5),Value(26));
+        assert_eq!(d.get_shortest_path_distance(6),NA);
+        assert_eq!(d.get_shortest_path_distances().len(),5);
+
+        d.calculate_shortest_paths_to(&g, 1, &[3,6]).unwrap();
+        assert_eq!(d.unreached_targets(),&[6]);
+        d.calculate_shortest_paths_to(&g, 1, &[6]).unwrap();
+        assert_eq!(d.unreached_targets(),&[6]);
+        assert_eq!(d.get_shortest_path_distances().len(),6);
+    }
+
+    #[test]
+    fn negative_edges() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+		assert_eq!((&mut g).add_edge(5,3,-2),Some(6));
+		assert_eq!((&mut g).add_edge(2,3,-1),Some(7));
+        let mut d = new_dijkstra(&g);
+        match d.calculate_shortest_paths(&g, 1) {
+            Err(Error::NegativeEdges(ids)) => assert_eq!(ids,vec!(6,7)),
+            _ => panic!("negative edges not detected"),
+        }
+        let mut d = Dijkstra::with_allow_negative(1);
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        assert!(d.calculate_shortest_paths(&g, 1).is_ok());
+        assert_eq!(d.get_shortest_path_distance(3),Value(6));
+    }
+
+    #[test]
+    fn multi_source() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths_multi(&g, &[(1,0),(4,5),(3,30)], &[]).unwrap();
+        let paths = d.get_shortest_paths();
+        assert_eq!((paths[&2].source,paths[&2].distance),(1,Value(7)));
+        assert_eq!((paths[&3].source,paths[&3].distance),(1,Value(9)));
+        assert_eq!(paths[&3].path,vec!(1,3));
+        assert_eq!((paths[&5].source,paths[&5].distance),(4,Value(11)));
+        assert_eq!(paths[&5].path,vec!(4,5));
+        assert_eq!((paths[&6].distance,paths[&6].path.clone()),(Max,vec!(6)));
+
+        let mut d = new_dijkstra(&g);
+        assert!(matches!(d.calculate_shortest_paths_multi(&g, &[(1,0),(9,0)], &[]),Err(Error::UnknownVertex(9))));
+    }
+
+    fn queue_distances<Q: VertexQueue>(g: &DirectedGraph, queue: Q) -> Vec<(usize,crate::minmax::MinMax<i64>)> {
+        let mut d = Dijkstra::with_queue(1, queue);
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        d.calculate_shortest_paths_multi(g, &[(1,0),(4,-3)], &[]).unwrap();
+        d.get_shortest_path_distances().into_iter().collect()
+    }
+
+    #[test]
+    fn queues() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+		(&mut g).add_edge(5,2,1);
+        let mut d = new_dijkstra(&g);
+        d.calculate_shortest_paths_multi(&g, &[(1,0),(4,-3)], &[]).unwrap();
+        let expected : Vec<_> = d.get_shortest_path_distances().into_iter().collect();
+        assert_eq!(expected[1],(2,Value(4)));
+        assert_eq!(queue_distances(&g, LazyBinaryHeap::new()),expected);
+        assert_eq!(queue_distances(&g, DialQueue::new()),expected);
+        assert_eq!(queue_distances(&g, RadixHeap::new()),expected);
+    }
+
+}
@@ -1,12 +1,15 @@
 //use std::process; use std::io::{self, Write}; // use std::error::Error;
 //use std::cmp;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use log::{  info, error, debug, /*warn ,*/ trace };
 
 use std::fmt::Display; 
 use std::fmt;
 
 use crate::graphbuilder::GraphBuilder;
+use crate::error::{Error, Result};
+use crate::cycle::Cycle;
+use crate::minmax::MinMax::Value;
 
 
 #[derive(Debug,Clone)]
@@ -15,6 +18,9 @@ pub struct Edge {
     source:  usize,
     dest:    usize,
     weight:  i64,
+    // dense indexes of the source and dest vertexes (see DirectedGraph::vertex_index)
+    source_index: usize,
+    dest_index: usize,
 }
 
 impl Display for Edge {
@@ -34,10 +40,17 @@ impl Edge {
             source:     source_vertex_id,
             dest:       dest_vertex_id,
             weight:     weight,
+            source_index: 0,
+            dest_index: 0,
         }
     }
 
 
+    /// Returns the id of the egde
+    pub fn id(&self) -> usize {
+        self.edge_id
+    }
+
     /// Returns the starting vertex of the egde
     pub fn source(&self) -> usize {
         self.source
@@ -52,6 +65,16 @@ impl Edge {
     pub fn weight(&self) -> i64 {
         self.weight
     }
+
+    /// Returns the dense index of the starting vertex of the edge
+    pub fn source_index(&self) -> usize {
+        self.source_index
+    }
+
+    /// Returns the dense index of the terminating vertex of the edge
+    pub fn dest_index(&self) -> usize {
+        self.dest_index
+    }
 }
 
 
@@ -59,29 +82,35 @@ impl Edge {
 #[derive(Debug, Clone)]
 pub struct Vertex {
 	vertex_id: usize,
+    // dense index of the vertex (see DirectedGraph::vertex_index)
+    index: usize,
     // set of incomin and outgoing edge ids
 	incoming: BTreeSet<usize>,
 	outgoing: BTreeSet<usize>,
     adjustment: i64,
 }
 
+impl Display for Vertex {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Vertex {}", self.vertex_id)
+    }
+
+}
+
 impl Vertex {
 
 	pub fn new(id : usize) -> Vertex {
 		let incoming = BTreeSet::<usize>::new();
 		let outgoing = BTreeSet::<usize>::new();
 		Vertex {vertex_id: id, 
+                index: 0,
 				incoming: incoming, 
 				outgoing: outgoing,
                 adjustment: 0,
 				}
 	}
 
-    
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Vertex {}", self.vertex_id)
-    }
-	
 	pub fn add_outgoing_edge_id(&mut self, edge_id: usize) {
         trace!("Adding outgoing edge {} to vertex {}",edge_id, self.vertex_id);
         if !self.outgoing.insert(edge_id) {
@@ -125,6 +154,11 @@ impl Vertex {
         self.vertex_id
     }
 
+    /// Returns the dense index of the vertex
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
     pub fn set_adjustment(&mut self, amount: i64) {
         self.adjustment = amount;
     }
@@ -145,7 +179,15 @@ pub struct DirectedGraph {
     ///Edge Map maps a edge Id to the Edge Data structure for it
     edge_map:   BTreeMap::<usize, Edge>,
     /// Edge Ids are automatically assiged by define edge and this is the ID of the next edge to be defined
-    next_edge_id:  usize
+    next_edge_id:  usize,
+    /// Vertex ids in the order they were defined -- the position of a vertex in this list is its
+    /// dense index, which allows the algorithms to use arrays indexed from 0 to vertex_count
+    /// regardless of the actual ids used
+    index_to_id:  Vec<usize>,
+    /// Optional labels for vertexes (vertex id -> label) read from the input
+    labels:  BTreeMap::<usize, String>,
+    /// Reverse map of labels to vertex ids
+    label_ids:  BTreeMap::<String, usize>,
 }
 
 
@@ -179,6 +221,10 @@ impl GraphBuilder for &mut DirectedGraph {
     fn add_vertex(&mut self, id:  usize) { 
         self.define_vertex(id);
     }
+
+    fn add_labeled_vertex(&mut self, label: &str, first_id: usize) -> Option<usize> {
+        Some(self.define_labeled_vertex_from(label, first_id))
+    }
 }
 
 
@@ -190,6 +236,9 @@ impl DirectedGraph {
 				vertex_map:     v_map,
 				edge_map:       e_map,
                 next_edge_id:   1,
+                index_to_id:    Vec::<usize>::new(),
+                labels:         BTreeMap::<usize, String>::new(),
+                label_ids:      BTreeMap::<String, usize>::new(),
 		}
 	}
 
@@ -201,17 +250,96 @@ impl DirectedGraph {
 		} 
 		else { 
             trace!("Adding Vertex {}",id);
-			let v = Vertex::new(id.clone());
+			let mut v = Vertex::new(id);
+            v.index = self.index_to_id.len();
+            self.index_to_id.push(id);
 			self.vertex_map.insert(id,v);
 			Some(self.vertex_map.len())  
 		}
     }
 
+    /// Defines a vertex identified by a label (if a vertex with the label doesn't already exist)
+    /// and returns its id.  New labels are assigned the next unused vertex id
+    pub fn define_labeled_vertex(&mut self, label: &str) -> usize {
+        self.define_labeled_vertex_from(label, 1)
+    }
+
+    /// Defines a vertex identified by a label like define_labeled_vertex, but assigns a new
+    /// label the next unused vertex id that is at least first_id
+    pub fn define_labeled_vertex_from(&mut self, label: &str, first_id: usize) -> usize {
+        if let Some(id) = self.label_ids.get(label) {
+            return *id;
+        }
+        let id = self.next_vertex_id().max(first_id);
+        trace!("Adding Vertex {} for label {}",id,label);
+        self.define_vertex(id);
+        self.labels.insert(id,label.to_string());
+        self.label_ids.insert(label.to_string(),id);
+        id
+    }
+
+    /// Returns the lowest vertex id above all of the ids currently in use
+    pub fn next_vertex_id(&self) -> usize {
+        match self.vertex_map.keys().next_back() {
+            Some(last) => last + 1,
+            None => 1,
+        }
+    }
+
+    /// Returns the dense index (from 0 to vertex_count - 1) of a vertex id
+    pub fn vertex_index(&self, id: usize) -> Option<usize> {
+        self.vertex_map.get(&id).map(|v| v.index)
+    }
+
+    /// Returns the vertex id for a dense index
+    pub fn vertex_id(&self, index: usize) -> Option<usize> {
+        self.index_to_id.get(index).cloned()
+    }
+
+    /// Returns the vertex ids in dense index order
+    pub fn vertex_ids_by_index(&self) -> &[usize] {
+        &self.index_to_id
+    }
+
+    /// Returns the label of a vertex if it has one
+    pub fn vertex_label(&self, id: usize) -> Option<&str> {
+        self.labels.get(&id).map(|l| l.as_str())
+    }
+
+    /// Returns the id of the vertex with the given label
+    pub fn vertex_by_label(&self, label: &str) -> Option<usize> {
+        self.label_ids.get(label).cloned()
+    }
+
+    /// Returns the name to use when displaying a vertex -- its label if it has one, or its id
+    pub fn vertex_name(&self, id: usize) -> String {
+        match self.labels.get(&id) {
+            Some(label) => label.clone(),
+            None => format!("{}",id),
+        }
+    }
+
+    /// Finds a vertex from a name as displayed by vertex_name -- either a label or a numeric id
+    pub fn find_vertex(&self, name: &str) -> Result<usize> {
+        if let Some(id) = self.label_ids.get(name) {
+            return Ok(*id);
+        }
+        match name.parse::<usize>() {
+            Ok(id) if self.vertex_map.contains_key(&id) => Ok(id),
+            Ok(id) => Err(Error::UnknownVertex(id)),
+            Err(_) => Err(Error::UnknownLabel(name.to_string())),
+        }
+    }
+
 	pub fn define_edge(&mut self, source: usize, dest: usize, weight: i64 ) -> Option<usize> {
     //    if source != 0 && dest != 0 {
             let edge_id = self.next_edge_id.clone();
             self.next_edge_id += 1;
-			let e = Edge::new(edge_id, source, dest, weight);
+            self.define_vertex(source);
+            self.define_vertex(dest);
+			let mut e = Edge::new(edge_id, source, dest, weight);
+            e.source_index = self.vertex_map[&source].index;
+            e.dest_index = self.vertex_map[&dest].index;
 			self.edge_map.insert(edge_id,e);
             Some(edge_id)
      //   }
@@ -224,72 +352,73 @@ impl DirectedGraph {
 
 
     
-	pub fn get_outgoing_edges(&self, vertex: usize) -> Vec<Edge>{
-		let v = self.vertex_map.get(&vertex).unwrap();
+    /// retreives a vector of outgoing edges from a given vertex
+	pub fn get_outgoing_edges(&self, vertex: usize) -> Result<Vec<Edge>>{
+		let v = self.vertex(vertex)?;
         // get the list of outgoing edges
         // by mapping each id to its dest element
         // NOTE: since edge list is coming from the vertex, this isn't handling the case where edge_map.get
         // returns 'None' ; this shouldn't occur, and will crash here if it did
-		v.get_outgoing_edge_ids().iter().map(|x| self.edge_map.get(&x).unwrap().clone()).collect()
+		Ok(v.get_outgoing_edge_ids().iter().map(|x| self.edge_map.get(x).unwrap().clone()).collect())
 		
 	}
 
     /// retreives a vector of outogoing vertex_id from a given vertex
-	pub fn get_outgoing_vertex_ids(&self, vertex: usize) -> Vec<usize>{
-		let v = self.vertex_map.get(&vertex).unwrap();
+	pub fn get_outgoing_vertex_ids(&self, vertex: usize) -> Result<Vec<usize>>{
+		let v = self.vertex(vertex)?;
         // get the list of vertexe that this vertex has outgoing edges to (i.e vertexes that )accessible from this vertex)
         // by mapping each id to its dest element
         // NOTE: since edge list is coming from the vertex, this isn't handling the case where edge_map.get
         // returns 'None' ; this shouldn't occur, and will crash here if it did
-		v.get_outgoing_edge_ids()
+		Ok(v.get_outgoing_edge_ids()
             .iter()
             .map(|x| {let e = self.edge_map.get(&x).unwrap(); e.dest }
             .clone())
-            .collect()
+            .collect())
 	}
 
 
-	pub fn get_outgoing_edge_ids(&self, vertex: usize) -> Vec<usize>{
-		let v = self.vertex_map.get(&vertex).unwrap();
-		v.get_outgoing_edge_ids()
+	pub fn get_outgoing_edge_ids(&self, vertex: usize) -> Result<Vec<usize>>{
+		let v = self.vertex(vertex)?;
+		Ok(v.get_outgoing_edge_ids())
     }
 
     /// retreives a vector of incoming edges to a given vertex
-	pub fn get_incoming_edges(&self, vertex: usize) -> Vec<Edge>{
-		let v = self.vertex_map.get(&vertex).unwrap();
+	pub fn get_incoming_edges(&self, vertex: usize) -> Result<Vec<Edge>>{
+		let v = self.vertex(vertex)?;
         // get the list of outgoing edges
         // by mapping each id to its dest element
         // NOTE: since edge list is coming from the vertex, this isn't handling the case where edge_map.get
         // returns 'None' ; this shouldn't occur, and will crash here if it did
-		v.get_incoming_edge_ids().iter().map(|x| self.edge_map.get(&x).unwrap().clone()).collect()
+		Ok(v.get_incoming_edge_ids().iter().map(|x| self.edge_map.get(x).unwrap().clone()).collect())
 		
 	}
 
     /// retreives a vector of incoming vertex_id from a given vertex
-	pub fn get_incoming_vertex_ids(&self, vertex: usize) -> Vec<usize>{
-		let v = self.vertex_map.get(&vertex).unwrap();
+	pub fn get_incoming_vertex_ids(&self, vertex: usize) -> Result<Vec<usize>>{
+		let v = self.vertex(vertex)?;
         // get the list of vertexes that have edges incoming to this vertex 
         // by mapping each id to its dest element
         // NOTE: since edge list is coming from the vertex, this isn't handling the case where edge_map.get
         // returns 'None' ; this shouldn't occur, and will crash here if it did
-		v.get_incoming_edge_ids()
+		Ok(v.get_incoming_edge_ids()
             .iter()
             .map(|x| {let e = self.edge_map.get(&x).unwrap(); e.source }
             .clone())
-            .collect()
+            .collect())
 	}
 
 
-	pub fn get_incoming_edge_ids(&self, vertex: usize) -> Vec<usize>{
-		let v = self.vertex_map.get(&vertex).unwrap();
-		v.get_incoming_edge_ids()
+	pub fn get_incoming_edge_ids(&self, vertex: usize) -> Result<Vec<usize>>{
+		let v = self.vertex(vertex)?;
+		Ok(v.get_incoming_edge_ids())
     }
 
     /// return the weight of the incoming connection from a given ource vertex (if it existss) or
     /// None
     ///
-    pub fn get_incoming_connection_weight(&self, source: usize, vertex: usize) -> Option<i64> {
-		let v = self.vertex_map.get(&vertex).unwrap();
+    pub fn get_incoming_connection_weight(&self, source: usize, vertex: usize) -> Result<Option<i64>> {
+		let v = self.vertex(vertex)?;
 
 		let find_result = v.get_incoming_edge_ids()
             .iter()
@@ -298,8 +427,8 @@ impl DirectedGraph {
         
         debug!("Incoming Result info looking for source {} as incoming to {} {:?}",source, vertex , find_result);
         match find_result {
-            None => None,
-            Some((_vertex,weight)) => Some(weight),
+            None => Ok(None),
+            Some((_vertex,weight)) => Ok(Some(weight)),
         }
     }
 
@@ -307,8 +436,8 @@ impl DirectedGraph {
     /// return the weight of the incoming connection from a given ource vertex (if it existss) or
     /// None
     ///
-    pub fn get_outgoing_connection_weight(&self, vertex: usize, dest: usize) -> Option<i64> {
-		let v = self.vertex_map.get(&vertex).unwrap();
+    pub fn get_outgoing_connection_weight(&self, vertex: usize, dest: usize) -> Result<Option<i64>> {
+		let v = self.vertex(vertex)?;
 
 		let find_result = v.get_outgoing_edge_ids()
             .iter()
@@ -317,12 +446,17 @@ impl DirectedGraph {
         
         debug!("get_outoing_conn_weight: dest {} outgoing from {} {:?}",dest, vertex , find_result);
         match find_result {
-            None => None,
-            Some((_vertex,weight)) => Some(weight),
+            None => Ok(None),
+            Some((_vertex,weight)) => Ok(Some(weight)),
         }
     }
 
 
+    // looks up a vertex reporting an error if it isn't defined
+    fn vertex(&self, id: usize) -> Result<&Vertex> {
+        self.vertex_map.get(&id).ok_or(Error::UnknownVertex(id))
+    }
+
     /// get an iterator to all of the vertexes in the graph
     pub fn vertex_iter(&self) -> std::collections::btree_map::Iter<'_, usize, Vertex> {
         self.vertex_map.iter()
@@ -353,6 +487,11 @@ impl DirectedGraph {
 		self.vertex_map.keys().cloned().collect()
 	}
 
+    /// get the ids of all the edges with a negative weight
+	pub fn get_negative_edge_ids(&self) -> Vec<usize> {
+		self.edge_map.iter().filter(|(_id, e)| e.weight < 0).map(|(id, _e)| *id).collect()
+	}
+
     /// get a complete list of edge ids in the graph
 	pub fn get_edge_ids(&self) -> Vec<usize> {
 		self.edge_map.keys().cloned().collect()
@@ -362,11 +501,13 @@ impl DirectedGraph {
         println!("Vertexes:");
 		for (key, value) in &self.vertex_map {
 //			let out_list : String = value.outgoing.iter().map(|x| {let e = self.edge_map.get(x).unwrap(); format!("e{} v{}(w{}) ; ",x,e.dest,e.weight) }).collect();
-			let out_list : String = value.outgoing.iter().map(|x| {let e = 
-                    self.edge_map.get(x)
-                        .unwrap_or(&Edge { edge_id: 0,source: 0, dest: 0, weight: 0 }); format!("{} ; ",e) })
-                        .collect();
-			println!("Vertex {} ({}) :  outgoing list: {}",key,value.vertex_id,out_list);
+			let out_list : String = value.outgoing.iter().map(|x| {
+                    match self.edge_map.get(x) {
+                        Some(e) => format!("{} ; ",e),
+                        None => format!("{} ; ",Edge::new(0,0,0,0)),
+                    } })
+                    .collect();
+			println!("Vertex {} ({}) :  outgoing list: {}",key,self.vertex_name(value.vertex_id),out_list);
 		}
         println!("Edges");
         for (key, value) in &self.edge_map {
@@ -377,7 +518,7 @@ impl DirectedGraph {
 					
 	}
 
-	pub fn delete_edge(&mut self,edge_id: usize) -> Result<(),String>  {
+	pub fn delete_edge(&mut self,edge_id: usize) -> std::result::Result<(),String>  {
 	
         if let Some(edge) = self.edge_map.get(&edge_id) {
             self.vertex_map.get_mut(&edge.source).unwrap().delete_outgoing_edge_id(edge_id)	;
@@ -407,7 +548,7 @@ impl DirectedGraph {
         for path_index in 0..path.len()-1 {
             let source = path[path_index];
             let dest = path[path_index+1];
-            if let Some(weight) = self.get_outgoing_connection_weight(source,dest) {
+            if let Ok(Some(weight)) = self.get_outgoing_connection_weight(source,dest) {
                 total_weight += weight;
                 info!("Vertex {} has an outgoing connection to Vertex {} with a weight of {}",source,dest,weight);
             }
@@ -421,6 +562,60 @@ impl DirectedGraph {
         
     }
 
+    /// Returns the vertex ids in topological order (every edge goes from a vertex to one later
+    /// in the order), with ties in vertex id order
+    /// Returns NotAcyclic with one of the cycles if the graph has a cycle
+    pub fn topological_sort(&self) -> Result<Vec<usize>> {
+        // number of incoming edges from vertexes not yet in the order (by dense index)
+        let mut in_degree = vec![0_usize; self.vertex_count()];
+        for edge in self.edge_map.values() {
+            in_degree[edge.dest_index] += 1;
+        }
+
+        let mut ready : VecDeque<usize> = self.vertex_map.iter()
+            .filter(|(_id, v)| in_degree[v.index] == 0)
+            .map(|(id, _v)| *id)
+            .collect();
+        let mut order = Vec::<usize>::with_capacity(self.vertex_count());
+        while let Some(id) = ready.pop_front() {
+            order.push(id);
+            for edge_id in &self.vertex_map[&id].outgoing {
+                let edge = &self.edge_map[edge_id];
+                in_degree[edge.dest_index] -= 1;
+                if in_degree[edge.dest_index] == 0 {
+                    ready.push_back(edge.dest);
+                }
+            }
+        }
+        if order.len() == self.vertex_count() {
+            return Ok(order);
+        }
+
+        // every vertex left has an incoming edge from another one left, so walking back along
+        // those edges must come around to a vertex already visited
+        let start = self.vertex_map.values().find(|v| in_degree[v.index] > 0).unwrap().vertex_id;
+        info!("Graph is not acyclic, {} vertexes are on or after a cycle",self.vertex_count() - order.len());
+        let mut predecessor = BTreeMap::new();
+        let mut predecessor_edge = BTreeMap::new();
+        let mut current = start;
+        while !predecessor.contains_key(&current) {
+            let edge = self.vertex_map[&current].incoming.iter()
+                .map(|edge_id| &self.edge_map[edge_id])
+                .find(|edge| in_degree[edge.source_index] > 0)
+                .unwrap();
+            predecessor.insert(current, Value(edge.source));
+            predecessor_edge.insert(current, (edge.edge_id, edge.weight));
+            current = edge.source;
+        }
+        let cycle = Cycle::from_predecessors(&predecessor, &predecessor_edge, &[start]).unwrap();
+        Err(Error::NotAcyclic(cycle))
+    }
+
+    /// Returns true if the graph has no cycles
+    pub fn is_acyclic(&self) -> bool {
+        self.topological_sort().is_ok()
+    }
+
     /// get an iterator to all of the edges in the graph
     pub fn adjust_edges(&mut self) {
 
@@ -430,7 +625,7 @@ impl DirectedGraph {
             let dest_adjustment = self.get_vertex_from_id(edge_info.dest).unwrap().adjustment(); 
             let adjusted_weight = edge_info.weight + source_adjustment - dest_adjustment;
 
-            let mut edge = self.edge_map.get_mut(&edge_id).unwrap();
+            let edge = self.edge_map.get_mut(&edge_id).unwrap();
             edge.weight = adjusted_weight;
 
         }
@@ -446,6 +641,7 @@ impl DirectedGraph {
 mod tests {
     use crate::dirgraph::DirectedGraph;
     use crate::graphbuilder::GraphBuilder;
+    use crate::error::Error;
     use log::{  info, error, debug, warn, trace };
 
 
@@ -475,10 +671,10 @@ mod tests {
 		assert_eq!(g.add_edge(2,3,1),Some(3));
 		assert_eq!(g.add_edge(2,4,22),Some(4));
 		assert_eq!(g.add_edge(3,4,33),Some(5));
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3]);
-		assert_eq!(g.get_outgoing_vertex_ids(2),&[3,4]);
-		assert_eq!(g.get_outgoing_vertex_ids(3),&[4]);
-		assert_eq!(g.get_outgoing_vertex_ids(4),&[]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3]);
+		assert_eq!(g.get_outgoing_vertex_ids(2).unwrap(),&[3,4]);
+		assert_eq!(g.get_outgoing_vertex_ids(3).unwrap(),&[4]);
+		assert_eq!(g.get_outgoing_vertex_ids(4).unwrap(),&[]);
 		graph
 	} 
 
@@ -507,27 +703,27 @@ mod tests {
         let mut g = &mut graph;
 		assert_eq!(g.add_edge(1,2,1),Some(1));
 //		println!("{:#?}",g);
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2]);
-		assert_eq!(g.get_incoming_vertex_ids(2),&[1]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2]);
+		assert_eq!(g.get_incoming_vertex_ids(2).unwrap(),&[1]);
 		assert_eq!(g.add_edge(1,3,1),Some(2));
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3]);
-		assert_eq!(g.get_incoming_vertex_ids(2),&[1]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3]);
+		assert_eq!(g.get_incoming_vertex_ids(2).unwrap(),&[1]);
 	}
 
 	#[test]
 	fn test_add_del() {
 		let mut graph = setup_basic1();
         let mut g = &mut graph;
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3]);
 		assert_eq!(g.add_edge(1,2,1),Some(6));
 //		println!("{:#?}",g);
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3,2]);
-		assert_eq!(g.get_outgoing_vertex_ids(2),&[3,4]);
-		assert_eq!(g.get_outgoing_vertex_ids(3),&[4]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3,2]);
+		assert_eq!(g.get_outgoing_vertex_ids(2).unwrap(),&[3,4]);
+		assert_eq!(g.get_outgoing_vertex_ids(3).unwrap(),&[4]);
 		assert_eq!(g.delete_edge(6),Ok(()));
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3]);
 		assert_eq!(g.delete_edge(1),Ok(()));
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[3]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[3]);
 		
 	}
 
@@ -537,12 +733,12 @@ mod tests {
 		let mut graph = setup_basic1();
         let mut g = &mut graph;
 //		println!("{:#?}",g);
-		assert_eq!(g.get_incoming_connection_weight(2,4),Some(22));
-		assert_eq!(g.get_incoming_connection_weight(1,4),None);
-		assert_eq!(g.get_outgoing_connection_weight(1,2),Some(1));
-		assert_eq!(g.get_outgoing_connection_weight(1,4),None);
+		assert_eq!(g.get_incoming_connection_weight(2,4).unwrap(),Some(22));
+		assert_eq!(g.get_incoming_connection_weight(1,4).unwrap(),None);
+		assert_eq!(g.get_outgoing_connection_weight(1,2).unwrap(),Some(1));
+		assert_eq!(g.get_outgoing_connection_weight(1,4).unwrap(),None);
 		assert_eq!(g.add_edge(1,4,44),Some(6));
-		assert_eq!(g.get_outgoing_connection_weight(1,4),Some(44));
+		assert_eq!(g.get_outgoing_connection_weight(1,4).unwrap(),Some(44));
 
 	}
 
@@ -553,4 +749,39 @@ mod tests {
 		assert_eq!(g.verify_path(vec!(1,2,3,4)),Some(35));
 		assert_eq!(g.verify_path(vec!(4,1)),None);
     }
+
+	#[test]
+	fn test_unknown_vertex() {
+		let mut graph = setup_basic1();
+        let g = &mut graph;
+		assert!(matches!(g.get_outgoing_edges(9),Err(Error::UnknownVertex(9))));
+		assert!(matches!(g.get_incoming_vertex_ids(7),Err(Error::UnknownVertex(7))));
+		assert_eq!(g.verify_path(vec!(1,9)),None);
+    }
+
+	#[test]
+	fn test_sparse_and_labeled_vertexes() {
+		let mut graph = DirectedGraph::new();
+        let mut g = &mut graph;
+		assert_eq!(g.add_edge(1000,7,3),Some(1));
+		let a = g.define_labeled_vertex("A");
+		assert_eq!(a,1001);
+		assert_eq!(g.define_labeled_vertex("A"),a);
+		assert_eq!(g.add_edge(7,a,4),Some(2));
+		assert_eq!(g.vertex_ids_by_index(),&[1000,7,1001]);
+		assert_eq!(g.vertex_index(7),Some(1));
+		assert_eq!(g.vertex_id(2),Some(1001));
+		assert_eq!(g.vertex_name(a),"A");
+		assert_eq!(g.vertex_name(7),"7");
+		assert_eq!(g.find_vertex("A").unwrap(),a);
+		assert_eq!(g.find_vertex("1000").unwrap(),1000);
+		assert_eq!(g.define_labeled_vertex_from("B",2000),2000);
+		assert_eq!(g.define_labeled_vertex_from("C",5),2001);
+		assert_eq!(g.define_labeled_vertex_from("B",3000),2000);
+		assert!(matches!(g.find_vertex("D"),Err(Error::UnknownLabel(_))));
+		assert!(matches!(g.find_vertex("5"),Err(Error::UnknownVertex(5))));
+		let e = g.get_outgoing_edges(7).unwrap()[0].clone();
+		assert_eq!((e.source_index(),e.dest_index()),(1,2));
+		assert_eq!(g.verify_path(vec!(1000,7,a)),Some(7));
+    }
 }
@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io;
+
+use crate::cycle::Cycle;
+
+/// Errors reported while reading graphs or running the shortest path algorithms
+#[derive(Debug)]
+pub enum Error {
+    /// The input could not be read
+    Io(io::Error),
+    /// A line of the input could not be parsed (line and column are 1 based)
+    Parse { line: usize, column: usize, message: String },
+    /// A vertex id was referenced that is not defined in the graph
+    UnknownVertex(usize),
+    /// A vertex label was referenced that is not defined in the graph
+    UnknownLabel(String),
+    /// The graph contains a negative cycle so shortest paths are not defined
+    NegativeCycle,
+    /// A vertex id needed by an algorithm is already in use in the graph
+    VertexIdCollision(usize),
+    /// The graph has edges with negative weights (edge ids) which the algorithm doesn't support
+    NegativeEdges(Vec<usize>),
+    /// The graph has a cycle (one of them is given) but the algorithm requires an acyclic graph
+    NotAcyclic(Cycle),
+}
+
+/// Result type used throughout the crate
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Parse { line, column, message } => write!(f, "Parse error at line {} column {}: {}", line, column, message),
+            Error::UnknownVertex(id) => write!(f, "Vertex {} is not in the graph", id),
+            Error::UnknownLabel(label) => write!(f, "Vertex labeled '{}' is not in the graph", label),
+            Error::NegativeCycle => write!(f, "Graph contains a negative cycle"),
+            Error::VertexIdCollision(id) => write!(f, "Vertex id {} is already in use", id),
+            Error::NegativeEdges(ids) => {
+                let id_list : Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                write!(f, "Graph has edges with negative weights (edges {})", id_list.join(","))
+            },
+            Error::NotAcyclic(cycle) => write!(f, "Graph is not acyclic, it has the cycle {}", cycle),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
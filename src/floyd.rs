@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap};
+
+use crate::dirgraph::DirectedGraph;
+use crate::ShortestPathInfo;
+use crate::cycle::Cycle;
+use crate::error::{Error, Result};
+
+use log::{ info, debug /*,warn */,  trace };
+
+use crate::bellman::Bellman;
+use crate::minmax::MinMax;
+use crate::minmax::MinMax::{Value};
+
+/// All pairs shortest paths using the Floyd-Warshall algorithm.  The distances are kept in a
+/// V x V matrix (by dense vertex index) which makes it a good fit for dense graphs
+#[derive(Debug)]
+pub struct FloydWarshall<'a> {
+        graph : &'a DirectedGraph,
+        /// distance from vertex i to vertex j is at i * num_vertex + j (by dense vertex index)
+        distance: Vec<MinMax<i64>>,
+        /// index of the vertex following vertex i in the shortest path from i to j (same layout
+        /// as distance) -- None if there is no path
+        next_hop: Vec<Option<usize>>,
+        /// vertex ids of the graph in dense index order
+        vertex_ids: Vec<usize>,
+        found_negative_cycle : bool,
+        negative_cycle: Option<Cycle>,
+        num_vertex: usize,
+}
+
+
+impl<'a> FloydWarshall<'a> {
+
+    pub fn new(graph: &'a DirectedGraph ) -> FloydWarshall<'a> {
+
+        let num_vertex = graph.vertex_count();
+
+        FloydWarshall {
+            graph,
+            distance: Vec::<MinMax<i64>>::new(),
+            next_hop: Vec::<Option<usize>>::new(),
+            vertex_ids: graph.vertex_ids_by_index().to_vec(),
+            found_negative_cycle : false,
+            negative_cycle: None,
+            num_vertex,
+        }
+
+    }
+
+    /// Find all shortest path from each vertex to all other vertes
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn calculate_shortest_paths(&mut self) -> Result<()> {
+        info!("Starting all shortest path analysis with Floyd-Warshall algorithm");
+        let n = self.num_vertex;
+        self.found_negative_cycle = false;
+        self.negative_cycle = None;
+
+        // start with the distance from each vertex to itself as 0 and the weight of the direct
+        // edges (the lowest one if there are several between the same vertexes)
+        self.distance = vec![MinMax::Max; n * n];
+        self.next_hop = vec![None; n * n];
+        for i in 0..n {
+            self.distance[i * n + i] = Value(0);
+            self.next_hop[i * n + i] = Some(i);
+        }
+        for (_id, edge) in self.graph.edge_iter() {
+            let pos = edge.source_index() * n + edge.dest_index();
+            if Value(edge.weight()) < self.distance[pos] {
+                self.distance[pos] = Value(edge.weight());
+                self.next_hop[pos] = Some(edge.dest_index());
+            }
+        }
+
+        let mut row_k = vec![MinMax::Max; n];
+        for k in 0..n {
+            if k % 100 == 0 {
+                info!("Floyd-Warshall iteration {}", k);
+            }
+            // copy row k so that row i can be updated while reading it (row k doesn't change
+            // during iteration k unless there is a negative cycle through k)
+            row_k.copy_from_slice(&self.distance[k * n..(k + 1) * n]);
+            for i in 0..n {
+                let through_k = match self.distance[i * n + k] {
+                    Value(dist) => dist,
+                    _ => continue,
+                };
+                let next_to_k = self.next_hop[i * n + k];
+                for (j, dist_kj) in row_k.iter().enumerate() {
+                    if let Value(dist_kj) = dist_kj {
+                        let new_distance = Value(through_k + dist_kj);
+                        if new_distance < self.distance[i * n + j] {
+                            self.distance[i * n + j] = new_distance;
+                            self.next_hop[i * n + j] = next_to_k;
+                        }
+                    }
+                }
+            }
+
+            // a vertex with a negative distance to itself is on (or reaches) a negative cycle
+            if let Some(i) = (0..n).find(|i| self.distance[i * n + i] < Value(0)) {
+                info!("Vertex {} has a negative distance to itself --- Graph has a negative cycle", self.vertex_ids[i]);
+                self.found_negative_cycle = true;
+                self.negative_cycle = self.find_negative_cycle(self.vertex_ids[i]);
+                return Err(Error::NegativeCycle);
+            }
+        }
+        trace!("Distances {:?}",self.distance);
+        Ok(())
+
+    }
+
+    // finds the edges of a negative cycle reachable from vertex using Bellman-Ford (which
+    // recovers the cycle from its predecessors)
+    fn find_negative_cycle(&self, vertex: usize) -> Option<Cycle> {
+        let mut bellman = Bellman::new();
+        if bellman.calculate_shortest_paths(self.graph, vertex).is_ok() {
+            bellman.negative_cycle()
+        }
+        else {
+            None
+        }
+    }
+
+    pub fn has_negative_cycle(&self) -> bool {
+        self.found_negative_cycle
+    }
+
+    /// Returns the negative cycle found while calculating the shortest paths (if any)
+    pub fn negative_cycle(&self) -> Option<Cycle> {
+        self.negative_cycle.clone()
+    }
+
+    /// Returns the shortest distance between two vertexes calculated by calculate_shortest_paths
+    /// Returns NA if either vertex is not in the graph or the paths haven't been calculated
+    pub fn get_shortest_path_distance(&self, source: usize, dest: usize) -> MinMax<i64> {
+        match (self.graph.vertex_index(source), self.graph.vertex_index(dest)) {
+            (Some(i), Some(j)) if !self.distance.is_empty() => self.distance[i * self.num_vertex + j],
+            _ => MinMax::NA,
+        }
+    }
+
+    // builds the path from source to dest (by dense index) from the next hop matrix
+    fn find_path(&self, source: usize, dest: usize) -> Vec<usize> {
+        let n = self.num_vertex;
+        let mut path = vec!(self.vertex_ids[source]);
+        let mut current = source;
+        while current != dest {
+            match self.next_hop[current * n + dest] {
+                Some(next) => current = next,
+                None => break,
+            }
+            path.push(self.vertex_ids[current]);
+            if path.len() > n {
+                debug!("Path from {} to {} is longer than the number of vertexes",self.vertex_ids[source],self.vertex_ids[dest]);
+                break;
+            }
+        }
+        path
+    }
+
+    fn path_info(&self, source: usize, dest: usize) -> ShortestPathInfo {
+        let distance = self.distance[source * self.num_vertex + dest];
+        if distance == MinMax::Max {
+            return ShortestPathInfo::unreachable(self.vertex_ids[source], self.vertex_ids[dest]);
+        }
+        let path = self.find_path(source, dest);
+        let path_len = path.len();
+        ShortestPathInfo {
+            source: self.vertex_ids[source],
+            dest: self.vertex_ids[dest],
+            distance,
+            path,
+            path_len,
+            has_negative_cycle: false,
+        }
+    }
+
+    /// Returns the shortest paths from the source vertex to every vertex (keyed by dest vertex)
+    /// as calculated by calculate_shortest_paths
+    pub fn get_shortest_paths(&self, source: usize) -> Result<BTreeMap<usize,ShortestPathInfo>> {
+        let source_index = self.graph.vertex_index(source).ok_or(Error::UnknownVertex(source))?;
+        Ok(self.graph.vertex_iter()
+            .map(|(id, v)| (*id, self.path_info(source_index, v.index())))
+            .collect())
+    }
+
+    /// Find the shortest paths from each of the sources (or every vertex if sources is empty)
+    /// to all other vertexes, passing the results for each source to report
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn calculate_shortest_paths_from<F>(&mut self, sources: &[usize], mut report: F) -> Result<()>
+        where F: FnMut(usize, BTreeMap<usize,ShortestPathInfo>) {
+
+        self.calculate_shortest_paths()?;
+        let start_list = if sources.is_empty() { self.graph.get_vertex_ids() } else { sources.to_vec() };
+        for start in start_list {
+            report(start,self.get_shortest_paths(start)?);
+        }
+        Ok(())
+    }
+
+    /// Find the shortest of all the shortest paths between any pair of vertexes
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn find_shortest_shortest_path(&mut self) -> Result<Option<ShortestPathInfo>> {
+        self.calculate_shortest_paths()?;
+
+        // check the pairs in vertex id order so that ties are resolved the same way as Johnson
+        let mut shortest: Option<(usize,usize)> = None;
+        let mut shortest_distance = MinMax::Max;
+        for (source_id, source) in self.graph.vertex_iter() {
+            for (dest_id, dest) in self.graph.vertex_iter() {
+                let distance = self.distance[source.index() * self.num_vertex + dest.index()];
+                if source_id != dest_id && distance < shortest_distance {
+                    shortest_distance = distance;
+                    shortest = Some((source.index(), dest.index()));
+                }
+            }
+        }
+        Ok(shortest.map(|(source, dest)| self.path_info(source, dest)))
+    }
+
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::floyd::FloydWarshall;
+    use crate::Johnson;
+    use crate::error::Error;
+    use crate::minmax::MinMax::{Value,Max};
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,12),Some(1));
+		assert_eq!(g.add_edge(1,3,-13),Some(2));
+		assert_eq!(g.add_edge(2,3,23),Some(3));
+		assert_eq!(g.add_edge(2,4,-24),Some(4));
+		assert_eq!(g.add_edge(3,4,34),Some(5));
+		assert_eq!(g.add_edge(4,5,-45),Some(6));
+	}
+
+    #[test]
+    fn basic() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut f = FloydWarshall::new(&g);
+        assert!(f.calculate_shortest_paths().is_ok());
+        assert_eq!(f.get_shortest_path_distance(1,5),Value(-57));
+        assert_eq!(f.get_shortest_path_distance(5,1),Max);
+        let paths = f.get_shortest_paths(1).unwrap();
+        assert_eq!(paths[&5].path,vec!(1,2,4,5));
+        assert_eq!(paths[&4].distance,Value(-12));
+        let shortest = f.find_shortest_shortest_path().unwrap().unwrap();
+        assert_eq!((shortest.source,shortest.dest,shortest.distance),(2,5,Value(-69)));
+        assert_eq!(shortest.path,vec!(2,4,5));
+    }
+
+    #[test]
+    fn matches_johnson() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+		assert_eq!((&mut g).add_edge(5,6,-30),Some(7));
+		assert_eq!((&mut g).add_edge(3,6,-1),Some(8));
+		assert_eq!((&mut g).add_edge(6,2,100),Some(9));
+        let mut j = Johnson::new(&g);
+        j.calculate_shortest_paths().unwrap();
+        let mut f = FloydWarshall::new(&g);
+        f.calculate_shortest_paths().unwrap();
+        for (start, results) in j.results_iter() {
+            for (dest, info) in results {
+                assert_eq!(f.get_shortest_path_distance(*start,*dest),info.distance);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_cycle() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+		assert_eq!((&mut g).add_edge(5,1,10),Some(7));
+        let mut f = FloydWarshall::new(&g);
+        assert!(matches!(f.find_shortest_shortest_path(),Err(Error::NegativeCycle)));
+        assert!(f.has_negative_cycle());
+        let cycle = f.negative_cycle().unwrap();
+        assert_eq!(cycle.vertexes,vec!(1,2,4,5));
+        assert_eq!(cycle.edges,vec!(1,4,6,7));
+        assert_eq!(cycle.weight,-47);
+    }
+
+}
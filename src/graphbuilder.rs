@@ -1,5 +1,13 @@
+/// Interface used by the file readers to populate a graph
 pub trait GraphBuilder {
+    /// Adds an edge from source to dest (creating the vertexes as needed) and returns the new edge id
     fn add_edge(&mut self, source: usize,dest: usize,weight: i64) -> Option<usize>;
+    /// Adds a vertex with the given id (if it doesn't already exist)
     fn add_vertex(&mut self, id:  usize); 
+    /// Adds a vertex identified by a label (if it doesn't already exist) and returns its id, or
+    /// None if the builder doesn't support labels (the default).  New labels are given ids of
+    /// at least first_id, so the caller can keep them clear of the numeric ids it uses
+    fn add_labeled_vertex(&mut self, _label: &str, _first_id: usize) -> Option<usize> {
+        None
+    }
 }
-
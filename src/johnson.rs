@@ -1,10 +1,13 @@
 extern crate two_d_array;
 use std::collections::{BTreeMap};
+use std::thread;
 //use two_d_array::TwoDArray;
 
 use crate::dirgraph::DirectedGraph;
 use crate::graphbuilder::GraphBuilder;
 use crate::ShortestPathInfo;
+use crate::cycle::Cycle;
+use crate::error::{Error, Result};
 
 use log::{ info, error, debug /*,warn */,  trace };
 
@@ -16,117 +19,227 @@ use crate::minmax::MinMax::{Value};
 
 #[derive(Debug)]
 pub struct Johnson<'a> {
-        graph : &'a mut DirectedGraph,
+        graph : &'a DirectedGraph,
         shortest_path_info:  BTreeMap::<usize,BTreeMap::<usize,ShortestPathInfo>>,
         found_negative_cycle : bool,
-        num_vertex: usize
+        negative_cycle: Option<Cycle>,
+        /// number of threads used to run the Dijkstra calculations for each source vertex
+        /// (0 uses the number of available cores)
+        threads: usize,
 
 }
 
+// number of source vertexes each thread calculates before the results are reported, which
+// limits the results held at once when reporting the paths from each source
+const SOURCES_PER_THREAD : usize = 8;
 
-impl<'a> Johnson<'a> {
+// Find the shortest paths from start in the reweighted graph and convert the distances back to
+// the original weights
+fn shortest_paths_from(g_prime: &DirectedGraph, adjustment_results: &BTreeMap<usize,MinMax<i64>>, start: usize) -> Result<BTreeMap<usize,ShortestPathInfo>> {
+    // the reweighting makes all the edge weights non negative, so the check can be skipped
+    let mut d = Dijkstra::with_allow_negative(start);
 
-    pub fn new(mut graph: &mut DirectedGraph ) -> Johnson {
+    for (id, _v) in g_prime.vertex_iter() {
+        d.initialize_vertex(*id);
+    }
+    d.calculate_shortest_paths(g_prime, start)?;
+    let mut results = d.get_shortest_paths();
+    debug!("Results for Starting Vertex {} AFTER adjustment correction", start);
+    for (vertex_id, info) in results.iter_mut() {
+        let mut updated_info = info.clone();
+        updated_info.distance = info.distance - adjustment_results[&start] + adjustment_results[vertex_id];
+        *info = updated_info
+    }
+    trace!("{:#?}",results);
+    Ok(results)
+}
 
-        // Create a vertex with id 0 with a 0 cost edge to all vertex
-        // to ensure there is  starting vertex that is connectted to 
-        // all other vertex to for Bellman anaylsys
-        if graph.define_vertex(0) == None {
-            error!("Vertex 0 aready exists can't continue");
-            panic!("Vertex 0 already exists")
+// Find the shortest of the shortest paths from each of the sources with a position in the list
+// of sources from first by stepping by step, returning the position of the source along with
+// the path so that results from different threads can be merged in the same order as a single
+// thread would find them
+fn shortest_shortest_from(g_prime: &DirectedGraph, adjustment_results: &BTreeMap<usize,MinMax<i64>>, sources: &[usize], first: usize, step: usize) -> Result<Option<(usize,ShortestPathInfo)>> {
+
+    let mut shortest_distance = MinMax::Max;
+    let mut shortest_path_info = None;
+
+    for (pos, start) in sources.iter().enumerate().skip(first).step_by(step) {
+        debug!("Shortest distance now {}",shortest_distance);
+        let mut d = Dijkstra::with_allow_negative(*start);
+
+        for (id, _v) in g_prime.vertex_iter() {
+            d.initialize_vertex(*id);
         }
-        else {
-            // add the edges to create connections between 0 and all other vertexes
-            for v in graph.get_vertex_ids() {
-                graph.add_edge(0,v,0);
+        if start % 100 == 0 {
+            info!("Calculating shortest paths from vertex {}", start);
+        }
+        d.calculate_shortest_paths(g_prime, *start)?;
+
+        if let Some(new_short_path_info) = d.get_shortest_shortest_path(shortest_distance,adjustment_results.clone()) {
+            if new_short_path_info.distance < shortest_distance {
+                info!("Found shorter path from {} dist {}",start,new_short_path_info.distance);
+                shortest_distance = new_short_path_info.distance;
+                shortest_path_info = Some((pos,new_short_path_info));
             }
         }
+    }
+    Ok(shortest_path_info)
+}
+
 
+impl<'a> Johnson<'a> {
 
-        let num_vertex = graph.vertex_count();
+    /// Creates a Johnson instance that runs the Dijkstra calculations for the source vertexes
+    /// on all of the available cores (use with_threads(graph, 1) to run them serially)
+    pub fn new(graph: &'a DirectedGraph ) -> Johnson<'a> {
 
         Johnson {
             graph    : graph,
             shortest_path_info:  BTreeMap::<usize,BTreeMap::<usize,ShortestPathInfo>>::new(),
             found_negative_cycle : false,
-            num_vertex:  num_vertex,
+            negative_cycle: None,
+            threads: 0,
         }
 
     }
 
-    /// Find all shortest path from each vertex to all other vertes
-    pub fn calculate_shortest_paths(&mut self) {
-        let mut adjustment_info = Bellman::new(self.num_vertex);
-        let mut g_prime  = DirectedGraph::new();
+    /// Creates a Johnson instance that runs the Dijkstra calculations for the source vertexes
+    /// on the given number of threads (0 uses the number of available cores).  The results are
+    /// the same as when using a single thread
+    pub fn with_threads(graph: &'a DirectedGraph, threads: usize ) -> Johnson<'a> {
+        let mut johnson = Johnson::new(graph);
+        johnson.threads = threads;
+        johnson
+    }
 
-        info!("Starting all shortest path analysis with Johnson algorithm");
-        self.found_negative_cycle = false;
+    /// Returns the number of threads used for the Dijkstra calculations
+    pub fn threads(&self) -> usize {
+        if self.threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        }
+        else {
+            self.threads
+        }
+    }
 
+    /// Builds a copy of the graph with the edge weights adjusted so that they are all non
+    /// negative (edge weight + source adjustment - dest adjustment), along with the adjustment
+    /// for each vertex.  The graph passed to new is not modified.
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    fn reweighted_graph(&mut self) -> Result<(DirectedGraph, BTreeMap<usize,MinMax<i64>>)> {
+        // Create a copy of the graph with a vertex with an unused id with a 0 cost edge to all
+        // vertex to ensure there is  starting vertex that is connectted to 
+        // all other vertex to for Bellman anaylsys
+        let mut augmented = self.graph.clone();
+        let virtual_vertex = augmented.next_vertex_id();
+        augmented.define_vertex(virtual_vertex);
+        for v in self.graph.get_vertex_ids() {
+            (&mut augmented).add_edge(virtual_vertex,v,0);
+        }
+
+        let mut adjustment_info = Bellman::new();
+        self.found_negative_cycle = false;
 
         info!("Staring Bellman");
-        adjustment_info.calculate_shortest_paths(self.graph, 0);
+        adjustment_info.calculate_shortest_paths(&augmented, virtual_vertex)?;
         let adjustment_results = adjustment_info.get_shortest_path_distances();
         self.found_negative_cycle = adjustment_info.has_negative_cycle();
+        self.negative_cycle = adjustment_info.negative_cycle();
         debug!("Adjustment negative_cycle? {}",self.found_negative_cycle );
         debug!("Adjustment results {:?} ", adjustment_results);
         info!("Bellman Complete");
 
+        if self.found_negative_cycle {
+            info!("Found Negative Cycle");
+            return Err(Error::NegativeCycle);
+        }
 
-        if !self.found_negative_cycle {
-            // create the graph with the adjusted edge weights cacluated as preveious edge +
-            // source_vertex adjustment - dest_vertext adjustment
-            // skip vertex 0 since we added that to ensure that there a connected graph from the
-            // starting vertex 
-            for (_id, edge) in self.graph.edge_iter() {
-                if edge.source() != 0 {
-                    if let (Value(source_adj), Value(dest_adj))  = (adjustment_results[&edge.source()], adjustment_results[&edge.dest()]) {
-                        let adj_weight =  edge.weight() + source_adj - dest_adj;
-                        (&mut g_prime).add_edge(edge.source(),edge.dest(),adj_weight);
-                    }
-                    else {
-                        error!("Non numeric adjustment values source: {} dest {} source adj: {} dest adj {}",
-                               edge.source(),
-                               edge.dest(),
-                               adjustment_results[&edge.source()],
-                               adjustment_results[&edge.dest()]
-                            );
-                    }
-                }
-                
-
+        // create the graph with the adjusted edge weights cacluated as preveious edge +
+        // source_vertex adjustment - dest_vertext adjustment, starting from a copy of the
+        // original graph so that the virtual vertex and its edges are not included
+        let mut g_prime = self.graph.clone();
+        for (id, v) in g_prime.vertex_iter_mut() {
+            if let Value(amount) = adjustment_results[id] {
+                v.set_adjustment(amount);
+            }
+            else {
+                error!("Non numeric adjustment value for vertex {} : {}", id, adjustment_results[id]);
             }
-            debug!("g_prime {:#?}",g_prime);
+        }
+        g_prime.adjust_edges();
+        trace!("g_prime {:#?}",g_prime);
+
+        Ok((g_prime, adjustment_results))
+    }
 
+    /// Find all shortest path from each vertex to all other vertes
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn calculate_shortest_paths(&mut self) -> Result<()> {
 
-            for start in 1..self.num_vertex {
-                let mut d = Dijkstra::new(start);
+        let mut shortest_path_info = BTreeMap::<usize,BTreeMap::<usize,ShortestPathInfo>>::new();
+        self.calculate_shortest_paths_from(&[], |start, results| {
+            shortest_path_info.insert(start,results);
+        })?;
+        self.shortest_path_info = shortest_path_info;
+        Ok(())
 
-                for (id, _v) in g_prime.vertex_iter() {
-                    d.initialize_vertex(id.clone());
-                }
-                d.calculate_shortest_paths(&g_prime, start);
-                let mut results = d.get_shortest_paths();
-                debug!("Results for Starting Vertex {} AFTER adjustment correction", start);
-                for (vertex_id, info) in results.iter_mut() {
-                    let mut updated_info = info.clone();
-                    updated_info.distance = info.distance - adjustment_results[&start] + adjustment_results[vertex_id];
-                    *info = updated_info
-                }
-                trace!("{:#?}",results);
-                self.shortest_path_info.insert(start,results);
+    }
 
-            }
+    /// Find the shortest paths from each of the sources (or every vertex if sources is empty)
+    /// to all other vertexes, passing the results for each source to report as soon as they are
+    /// calculated rather than keeping the results for all the sources
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn calculate_shortest_paths_from<F>(&mut self, sources: &[usize], mut report: F) -> Result<()>
+        where F: FnMut(usize, BTreeMap<usize,ShortestPathInfo>) {
+
+        info!("Starting all shortest path analysis with Johnson algorithm");
+        let (g_prime, adjustment_results) = self.reweighted_graph()?;
 
+        let start_list = if sources.is_empty() { g_prime.get_vertex_ids() } else { sources.to_vec() };
+        let threads = self.threads();
+        if threads <= 1 {
+            for start in start_list {
+                report(start,shortest_paths_from(&g_prime, &adjustment_results, start)?);
+            }
+            return Ok(());
         }
-        else {
-            info!("Found Negative Cycle")
+
+        info!("Calculating shortest paths with {} threads", threads);
+        for batch in start_list.chunks(threads * SOURCES_PER_THREAD) {
+            // each thread takes every threads'th source in the batch, and the results are then
+            // put back in source order before being reported
+            let mut batch_results = thread::scope(|scope| {
+                let workers : Vec<_> = (0..threads).map(|first| {
+                    let g_prime = &g_prime;
+                    let adjustment_results = &adjustment_results;
+                    scope.spawn(move || {
+                        batch.iter().enumerate().skip(first).step_by(threads)
+                            .map(|(pos, start)| Ok((pos, *start, shortest_paths_from(g_prime, adjustment_results, *start)?)))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                }).collect();
+                workers.into_iter()
+                    .map(|worker| worker.join().expect("Johnson worker thread panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?.into_iter().flatten().collect::<Vec<_>>();
+
+            batch_results.sort_by_key(|(pos, _, _)| *pos);
+            for (_pos, start, results) in batch_results {
+                report(start,results);
+            }
         }
+        Ok(())
 
     }
 
     pub fn has_negative_cycle(&self) -> bool {
         self.found_negative_cycle
     }
+
+    /// Returns the negative cycle found while calculating the vertex adjustments (if any)
+    pub fn negative_cycle(&self) -> Option<Cycle> {
+        self.negative_cycle.clone()
+    }
     
         
     pub fn results_iter(&self) -> std::collections::btree_map::Iter<'_, usize, BTreeMap::<usize,ShortestPathInfo>>
@@ -154,97 +267,40 @@ impl<'a> Johnson<'a> {
 
 
 
-    /// Find all shortest path from each vertex to all other vertes
-    pub fn find_shortest_shortest_path(&mut self) -> Option<ShortestPathInfo> {
-        let mut adjustment_info = Bellman::new(self.num_vertex);
-        //let mut g_prime  = DirectedGraph::new();
+    /// Find the shortest of all the shortest paths between any pair of vertexes
+    /// Returns NegativeCycle if the graph contains a negative cycle
+    pub fn find_shortest_shortest_path(&mut self) -> Result<Option<ShortestPathInfo>> {
 
         info!("Starting all shortest path analysis with Johnson algorithm");
-        self.found_negative_cycle = false;
-
+        let (g_prime, adjustment_results) = self.reweighted_graph()?;
 
-        info!("Staring Bellman");
-        adjustment_info.calculate_shortest_paths(self.graph, 0);
-        let adjustment_results = adjustment_info.get_shortest_path_distances();
-        self.found_negative_cycle = adjustment_info.has_negative_cycle();
-        debug!("Adjustment negative_cycle? {}",self.found_negative_cycle );
-        debug!("Adjustment results {:?} ", adjustment_results);
-
-
-        if !self.found_negative_cycle {
-            for (id, v) in self.graph.vertex_iter_mut() {
-                if let Value(amount) = adjustment_results[id] {
-                    v.set_adjustment(amount);
-                } 
-
-            }
-
-            /*
-            // create the graph with the adjusted edge weights cacluated as preveious edge +
-            // source_vertex adjustment - dest_vertext adjustment
-            // skip vertex 0 since we added that to ensure that there a connected graph from the
-            // starting vertex 
-            for (_id, edge) in self.graph.edge_iter() {
-                if edge.source() != 0 {
-                    if let (Value(source_adj), Value(dest_adj))  = (adjustment_results[&edge.source()], adjustment_results[&edge.dest()]) {
-                        let adj_weight =  edge.weight() + source_adj - dest_adj;
-                        (&mut g_prime).add_edge(edge.source(),edge.dest(),adj_weight);
-                    }
-                    else {
-                        error!("Non numeric adjustment values source: {} dest {} source adj: {} dest adj {}",
-                               edge.source(),
-                               edge.dest(),
-                               adjustment_results[&edge.source()],
-                               adjustment_results[&edge.dest()]
-                            );
-                    }
-                }
-            }
-            debug!("g_prime {:#?}",g_prime);
-            */
-
-            self.graph.adjust_edges();
-
-            let mut shortest_distance = MinMax::Max;
-            let mut shortest_path_info = None;
-
-            for start in 1..self.num_vertex {
-                debug!("Shortest distance now {}",shortest_distance);
-                let mut d = Dijkstra::new(start);
-
-                for (id, _v) in self.graph.vertex_iter() {
-                    d.initialize_vertex(id.clone());
-                }
-                if start % 100 == 0 {
-                    info!("Calculating shortest paths from vertex {}", start);
-                }
-                d.calculate_shortest_paths(&self.graph, start);
-
-                if let Some(new_short_path_info) = d.get_shortest_shortest_path(shortest_distance,adjustment_results.clone()) {
-                    if new_short_path_info.distance < shortest_distance {
-                        info!("Found shorter path from {} dist {}",start,new_short_path_info.distance);
-                        shortest_distance = new_short_path_info.distance;
-                        shortest_path_info = Some(new_short_path_info);
-                    }
-                }
-            }
-
-            /*
-            if ! shortest_path_info.is_none() {
-                let unadjusted = shortest_path_info.unwrap();
-                let mut new_info = unadjusted.clone();
-                new_info.distance = unadjusted.distance - adjustment_results[&unadjusted.source] + adjustment_results[&unadjusted.dest];
-                shortest_path_info = Some(new_info);
-            }
-            */
-            shortest_path_info
-
-        }
-        else {
-            info!("Found Negative Cycle");
-            None
+        let start_list = g_prime.get_vertex_ids();
+        let threads = self.threads();
+        if threads <= 1 {
+            return Ok(shortest_shortest_from(&g_prime, &adjustment_results, &start_list, 0, 1)?.map(|(_pos, info)| info));
         }
 
+        info!("Calculating shortest paths with {} threads", threads);
+        let worker_results = thread::scope(|scope| {
+            let workers : Vec<_> = (0..threads).map(|first| {
+                let g_prime = &g_prime;
+                let adjustment_results = &adjustment_results;
+                let start_list = &start_list;
+                scope.spawn(move || shortest_shortest_from(g_prime, adjustment_results, start_list, first, threads))
+            }).collect();
+            workers.into_iter()
+                .map(|worker| worker.join().expect("Johnson worker thread panicked"))
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        // pick the shortest path, taking the one from the earliest source if there is a tie so
+        // that the result is the same as from a single thread
+        let shortest_path_info = worker_results.into_iter()
+            .flatten()
+            .min_by_key(|(pos, info)| (info.distance, *pos))
+            .map(|(_pos, info)| info);
+        Ok(shortest_path_info)
+
     }
 
 
@@ -256,6 +312,8 @@ mod tests {
     use crate::dirgraph::DirectedGraph;
     use crate::graphbuilder::GraphBuilder;
     use crate::Johnson;
+    use crate::error::Error;
+    use crate::minmax::MinMax;
     use log::{  info , /*error, debug, warn, trace */ };
 
     fn init() {
@@ -270,10 +328,10 @@ mod tests {
 		assert_eq!(g.add_edge(2,4,-24),Some(4));
 		assert_eq!(g.add_edge(3,4,34),Some(5));
 		assert_eq!(g.add_edge(4,5,-45),Some(6));
-		assert_eq!(g.get_outgoing_vertex_ids(1),&[2,3]);
-		assert_eq!(g.get_outgoing_vertex_ids(2),&[3,4]);
-		assert_eq!(g.get_outgoing_vertex_ids(3),&[4]);
-		assert_eq!(g.get_outgoing_vertex_ids(4),&[5]);
+		assert_eq!(g.get_outgoing_vertex_ids(1).unwrap(),&[2,3]);
+		assert_eq!(g.get_outgoing_vertex_ids(2).unwrap(),&[3,4]);
+		assert_eq!(g.get_outgoing_vertex_ids(3).unwrap(),&[4]);
+		assert_eq!(g.get_outgoing_vertex_ids(4).unwrap(),&[5]);
 	} 
 
     #[test]
@@ -282,8 +340,97 @@ mod tests {
 		let mut g = DirectedGraph::new();
         setup_basic(&mut g); 
         info!("basic Setup complete");
-        let mut j = Johnson::<'_>::new(&mut g);
-        j.calculate_shortest_paths();
+        let mut j = Johnson::<'_>::new(&g);
+        assert!(j.calculate_shortest_paths().is_ok());
+    }
+
+    #[test]
+    fn graph_unchanged() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+        let before = format!("{:?}",g);
+        let mut j = Johnson::<'_>::new(&g);
+        assert!(j.find_shortest_shortest_path().unwrap().is_some());
+        assert!(j.calculate_shortest_paths().is_ok());
+        assert_eq!(format!("{:?}",g),before);
+        assert_eq!(g.vertex_count(),5);
+        assert_eq!(g.verify_path(vec!(1,2,4,5)),Some(-57));
+    }
+
+    #[test]
+    fn all_pairs_from_sources() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+        let mut j = Johnson::<'_>::new(&g);
+        let mut reported = Vec::<usize>::new();
+        j.calculate_shortest_paths_from(&[2,4], |start, results| {
+            reported.push(start);
+            if start == 2 {
+                assert_eq!(results[&5].distance,MinMax::Value(-69));
+                assert_eq!(results[&5].path,vec!(2,4,5));
+                assert_eq!(results[&1].distance,MinMax::Max);
+            }
+        }).unwrap();
+        assert_eq!(reported,vec!(2,4));
+
+        j.calculate_shortest_paths().unwrap();
+        assert_eq!(j.results_iter().count(),5);
+        assert_eq!(j.shortest_shortest_path(),(MinMax::Value(-69),vec!(2,4,5)));
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+		assert_eq!((&mut g).add_edge(5,6,-30),Some(7));
+		assert_eq!((&mut g).add_edge(3,6,-1),Some(8));
+        assert!(Johnson::<'_>::new(&g).threads() >= 1);
+        let mut serial = Johnson::<'_>::with_threads(&g, 1);
+        assert_eq!(serial.threads(),1);
+        let mut serial_results = Vec::new();
+        serial.calculate_shortest_paths_from(&[], |start, results| serial_results.push((start, format!("{:?}",results)))).unwrap();
+        let serial_shortest = serial.find_shortest_shortest_path().unwrap().unwrap();
+
+        for threads in [0, 2, 3, 7] {
+            let mut parallel = if threads == 0 { Johnson::<'_>::new(&g) } else { Johnson::<'_>::with_threads(&g, threads) };
+            let mut parallel_results = Vec::new();
+            parallel.calculate_shortest_paths_from(&[], |start, results| parallel_results.push((start, format!("{:?}",results)))).unwrap();
+            assert_eq!(parallel_results,serial_results);
+            let shortest = parallel.find_shortest_shortest_path().unwrap().unwrap();
+            assert_eq!((shortest.source,shortest.dest,shortest.distance,shortest.path),
+                       (serial_shortest.source,serial_shortest.dest,serial_shortest.distance,serial_shortest.path.clone()));
+        }
+    }
+
+    #[test]
+    fn vertex_zero() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+		assert_eq!((&mut g).add_edge(0,1,-20),Some(7));
+        let mut j = Johnson::<'_>::new(&g);
+        let shortest = j.find_shortest_shortest_path().unwrap().unwrap();
+        assert_eq!(shortest.source,0);
+        assert_eq!(shortest.dest,5);
+        assert_eq!(shortest.distance,MinMax::Value(-77));
+    }
+
+    #[test]
+    fn negative_cycle() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g); 
+		assert_eq!((&mut g).add_edge(5,1,10),Some(7));
+        let mut j = Johnson::<'_>::new(&g);
+        assert!(matches!(j.find_shortest_shortest_path(),Err(Error::NegativeCycle)));
+        assert!(j.has_negative_cycle());
+        let cycle = j.negative_cycle().unwrap();
+        assert_eq!(cycle.vertexes,vec!(1,2,4,5));
+        assert_eq!(cycle.edges,vec!(1,4,6,7));
+        assert_eq!(cycle.weight,-47);
     }
 
 }
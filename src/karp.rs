@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap};
+
+use crate::dirgraph::DirectedGraph;
+use crate::bellman::Bellman;
+use crate::cycle::Cycle;
+use crate::error::Result;
+
+use log::{ info, debug, trace };
+use crate::minmax::{MinMax,MinMax::Value};
+
+/// Minimum mean weight cycle using Karp's algorithm.  With D_k(v) the weight of the shortest
+/// walk of exactly k edges ending at v (starting anywhere), the minimum mean is
+/// min over v of max over k of (D_n(v) - D_k(v)) / (n - k), and a cycle with that mean is on
+/// the shortest walk of n edges to the vertex giving the minimum
+pub struct MinMeanCycle<'a> {
+        graph: &'a DirectedGraph,
+        /// D_k(v) for each iteration k (row) and vertex (column, by dense index)
+        walks: Option<Bellman>,
+}
+
+impl<'a> MinMeanCycle<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> MinMeanCycle<'a> {
+        MinMeanCycle { graph, walks: None }
+    }
+
+    /// Finds a cycle with the lowest mean edge weight, or None if the graph has no cycles
+    pub fn find_min_mean_cycle(&mut self) -> Result<Option<Cycle>> {
+        let n = self.graph.vertex_count();
+        info!("Finding minimum mean cycle of {} vertexes",n);
+        if n == 0 {
+            return Ok(None);
+        }
+
+        // starting every vertex at 0 is the same as walks from a virtual source with 0 weight
+        // edges to every vertex
+        let sources : Vec<(usize,i64)> = self.graph.get_vertex_ids().into_iter().map(|id| (id, 0)).collect();
+        let mut bellman = Bellman::with_walk_history();
+        bellman.calculate_shortest_paths_multi(self.graph, &sources)?;
+        self.walks = Some(bellman);
+
+        // the mean for each vertex is the max over k of the fraction, kept as (weight, edges)
+        // to compare exactly
+        let mut best : Option<(usize,(i64,i64))> = None;
+        for v in 0..n {
+            let d_n = match self.walk_distance(v, n) {
+                Value(distance) => distance,
+                _ => continue,
+            };
+            let vertex_mean = (0..n)
+                .filter_map(|k| match self.walk_distance(v, k) {
+                    Value(d_k) => Some((d_n - d_k, (n - k) as i64)),
+                    _ => None,
+                })
+                .max_by(|a, b| compare_means(*a, *b));
+            if let Some(mean) = vertex_mean {
+                trace!("Vertex {} mean {}/{}",self.graph.vertex_id(v).unwrap(),mean.0,mean.1);
+                let better = match best {
+                    Some((_, best_mean)) => compare_means(mean, best_mean).is_lt(),
+                    None => true,
+                };
+                if better {
+                    best = Some((v, mean));
+                }
+            }
+        }
+
+        let (v, mean) = match best {
+            Some(best) => best,
+            None => {
+                info!("Graph has no cycles");
+                return Ok(None);
+            }
+        };
+        debug!("Minimum mean {}/{} found at vertex {}",mean.0,mean.1,self.graph.vertex_id(v).unwrap());
+        Ok(self.cycle_on_walk(v, n))
+    }
+
+    // D_k(v) for the dense index of v
+    fn walk_distance(&self, index: usize, k: usize) -> MinMax<i64> {
+        match self.walks.as_ref().and_then(|walks| walks.history()) {
+            Some(history) => history.get(index, k).unwrap_or(MinMax::Max),
+            None => MinMax::Max,
+        }
+    }
+
+    // follows the shortest walk of k edges back from the vertex (by dense index), splitting
+    // off the cycles where it repeats a vertex and returning the one with the lowest mean
+    fn cycle_on_walk(&self, index: usize, k: usize) -> Option<Cycle> {
+        // vertexes and edges (id, weight) of the walk, walked backwards from the vertex
+        let mut vertexes = vec!(self.graph.vertex_id(index).unwrap());
+        let mut edges = Vec::<(usize,i64)>::new();
+        let mut current = index;
+        for level in (1..=k).rev() {
+            let distance = self.walk_distance(current, level);
+            let edge = self.graph.get_incoming_edges(vertexes[vertexes.len() - 1]).ok()?
+                .into_iter()
+                .find(|e| self.walk_distance(e.source_index(), level - 1) + Value(e.weight()) == distance)?;
+            vertexes.push(edge.source());
+            edges.push((edge.id(), edge.weight()));
+            current = edge.source_index();
+        }
+        vertexes.reverse();
+        edges.reverse();
+
+        // position of each vertex on the remaining walk, so a repeated vertex closes a cycle
+        let mut position = BTreeMap::<usize,usize>::new();
+        let mut stack_vertexes = Vec::<usize>::new();
+        let mut stack_edges = Vec::<(usize,i64)>::new();
+        let mut best : Option<Cycle> = None;
+        for (pos, vertex) in vertexes.iter().enumerate() {
+            if let Some(start) = position.get(vertex).cloned() {
+                let cycle_edges : Vec<(usize,i64)> = stack_edges.drain(start..).chain(std::iter::once(edges[pos - 1])).collect();
+                let cycle_vertexes : Vec<usize> = stack_vertexes.drain(start..).collect();
+                for v in &cycle_vertexes {
+                    position.remove(v);
+                }
+                let weight = cycle_edges.iter().map(|(_id, weight)| weight).sum();
+                let cycle = Cycle::new(cycle_vertexes, cycle_edges.iter().map(|(id, _weight)| *id).collect(), weight);
+                trace!("Walk has cycle {}",cycle);
+                let better = match &best {
+                    Some(b) => compare_means((cycle.weight, cycle.len() as i64), (b.weight, b.len() as i64)).is_lt(),
+                    None => true,
+                };
+                if better {
+                    best = Some(cycle);
+                }
+            }
+            else if pos > 0 {
+                stack_edges.push(edges[pos - 1]);
+            }
+            position.insert(*vertex, stack_vertexes.len());
+            stack_vertexes.push(*vertex);
+        }
+        best
+    }
+}
+
+// compares the means weight_a / edges_a and weight_b / edges_b (edges are positive)
+fn compare_means(a: (i64,i64), b: (i64,i64)) -> std::cmp::Ordering {
+    (a.0 as i128 * b.1 as i128).cmp(&(b.0 as i128 * a.1 as i128))
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::karp::MinMeanCycle;
+
+    #[test]
+    fn basic() {
+        // cycles 1,2,3 (mean 2), 2,4 (mean 1.5) and 4,5,6,7 (mean 1.25)
+		let mut g = DirectedGraph::new();
+		for (source, dest, weight) in [(1,2,1),(2,3,2),(3,1,3),(2,4,1),(4,2,2),(4,5,0),(5,6,3),(6,7,0),(7,4,2),(7,8,-5)] {
+			(&mut g).add_edge(source,dest,weight);
+		}
+        let cycle = MinMeanCycle::new(&g).find_min_mean_cycle().unwrap().unwrap();
+        assert_eq!(cycle.vertexes,vec!(4,5,6,7));
+        assert_eq!(cycle.edges,vec!(6,7,8,9));
+        assert_eq!(cycle.weight,5);
+        assert_eq!(cycle.mean_weight(),1.25);
+
+		assert_eq!((&mut g).add_edge(3,2,-5),Some(11));
+        let cycle = MinMeanCycle::new(&g).find_min_mean_cycle().unwrap().unwrap();
+        assert_eq!((cycle.vertexes,cycle.weight),(vec!(2,3),-3));
+    }
+
+    #[test]
+    fn separate_components() {
+        // 1,2 has the lowest total weight but 3,4,5 the lowest mean, and neither can reach
+        // the other
+		let mut g = DirectedGraph::new();
+		for (source, dest, weight) in [(1,2,1),(2,1,-3),(3,4,-1),(4,5,-1),(5,3,-2)] {
+			(&mut g).add_edge(source,dest,weight);
+		}
+        let cycle = MinMeanCycle::new(&g).find_min_mean_cycle().unwrap().unwrap();
+        assert_eq!((cycle.vertexes.len(),cycle.weight),(3,-4));
+    }
+
+    #[test]
+    fn acyclic() {
+		let mut g = DirectedGraph::new();
+		(&mut g).add_edge(1,2,1);
+		(&mut g).add_edge(2,3,-1);
+        assert!(MinMeanCycle::new(&g).find_min_mean_cycle().unwrap().is_none());
+        assert!(MinMeanCycle::new(&DirectedGraph::new()).find_min_mean_cycle().unwrap().is_none());
+    }
+
+}
@@ -0,0 +1,87 @@
+//! Shortest path utilities for weighted directed graphs.
+//!
+//! The crate is organized around [`DirectedGraph`], which holds the vertexes and edges of a
+//! graph, and a set of solvers that operate on it:
+//!
+//! * [`Dijkstra`] -- single source shortest paths for graphs with non-negative weights, with a
+//!   choice of [`VertexQueue`] (e.g. [`DialQueue`] or [`RadixHeap`] for small integer weights)
+//! * [`BidirectionalDijkstra`] -- point to point shortest path searching from both ends
+//! * [`DeltaStepping`] -- single source shortest paths for large graphs with non-negative
+//!   weights, relaxing the edges of each bucket of vertexes in parallel
+//! * [`Bellman`] -- single source shortest paths (Bellman-Ford) with negative cycle detection
+//! * [`Johnson`] -- all pairs shortest paths using Bellman-Ford reweighting and Dijkstra
+//! * [`FloydWarshall`] -- all pairs shortest paths for dense graphs
+//! * [`DagPaths`] -- shortest or longest paths on acyclic graphs in topological order
+//! * [`DFS`] -- depth first search and strongly connected components
+//! * [`NegativeCycleSearch`] -- the negative cycles of each strongly connected component
+//! * [`MinMeanCycle`] -- the cycle with the lowest mean edge weight (Karp's algorithm)
+//! * [`AStar`] -- shortest path between two vertexes guided by a [`Heuristic`]
+//! * [`Yen`] -- the k shortest loopless paths between two vertexes
+//! * [`ContractionHierarchy`] -- preprocessed graph for fast repeated point to point queries
+//! * [`DifferenceConstraints`] -- systems of difference constraints solved with Bellman-Ford
+//!
+//! Graphs can be built directly through the [`GraphBuilder`] trait or loaded from a file in
+//! adjacency list format with [`read_adjacency_multi`].  Failures are reported through the
+//! crate wide [`Error`] type.
+//!
+//! ```
+//! use short::{DirectedGraph, Dijkstra, GraphBuilder, MinMax};
+//!
+//! let mut g = DirectedGraph::new();
+//! (&mut g).add_edge(1, 2, 5);
+//! (&mut g).add_edge(2, 3, 1);
+//! (&mut g).add_edge(1, 3, 9);
+//!
+//! let mut d = Dijkstra::new(1);
+//! for id in g.get_vertex_ids() {
+//!     d.initialize_vertex(id);
+//! }
+//! d.calculate_shortest_paths(&g, 1).unwrap();
+//! assert_eq!(d.get_shortest_path_distance(3), MinMax::Value(6));
+//! ```
+
+pub mod error;
+pub mod graphbuilder;
+pub mod shortpathinfo;
+pub mod dirgraph;
+pub mod dijkstra;
+pub mod queue;
+pub mod bidirectional;
+pub mod delta;
+pub mod bellman;
+pub mod johnson;
+pub mod floyd;
+pub mod dag;
+pub mod dfs;
+pub mod negcycle;
+pub mod karp;
+pub mod astar;
+pub mod yen;
+pub mod contraction;
+pub mod constraints;
+pub mod parse;
+pub mod minmax;
+pub mod cycle;
+
+pub use crate::error::Error;
+pub use crate::graphbuilder::GraphBuilder;
+pub use crate::shortpathinfo::ShortestPathInfo;
+pub use crate::dirgraph::{DirectedGraph, Edge, Vertex};
+pub use crate::dijkstra::Dijkstra;
+pub use crate::queue::{VertexQueue, LazyBinaryHeap, DialQueue, RadixHeap};
+pub use crate::bidirectional::BidirectionalDijkstra;
+pub use crate::delta::DeltaStepping;
+pub use crate::bellman::Bellman;
+pub use crate::johnson::Johnson;
+pub use crate::floyd::FloydWarshall;
+pub use crate::dag::DagPaths;
+pub use crate::dfs::{DFS, StronglyConnectedComponents};
+pub use crate::negcycle::{NegativeCycleSearch, ComponentNegativeCycle};
+pub use crate::karp::MinMeanCycle;
+pub use crate::astar::{AStar, Heuristic, ZeroHeuristic, LandmarkHeuristic, LandmarkSelection, CoordinateHeuristic};
+pub use crate::yen::Yen;
+pub use crate::contraction::{ContractionHierarchy, HierarchyEdge};
+pub use crate::constraints::{DifferenceConstraints, Constraint, ConstraintSolution};
+pub use crate::parse::{read_adjacency_multi, read_coordinates, read_constraints};
+pub use crate::minmax::MinMax;
+pub use crate::cycle::Cycle;
@@ -9,47 +9,34 @@ use std::collections::BTreeMap;
 use clap::Parser;
 
 
-mod graphbuilder;
 mod cmd_line;
-mod shortpathinfo;
-mod dirgraph;
-mod dijkstra;
-mod bellman;
-mod johnson;
-mod parse;
-mod minmax;
 
 use crate::cmd_line::CommandArgs;
 use crate::cmd_line::Commands;
-use crate::dirgraph::DirectedGraph;
-use crate::dijkstra::Dijkstra;
-use crate::bellman::Bellman;
-use crate::minmax::MinMax;
-use crate::johnson::Johnson;
-use crate::parse::{read_adjacency_multi };
-use crate::shortpathinfo::ShortestPathInfo;
+use crate::cmd_line::HeuristicKind;
+use crate::cmd_line::LandmarkKind;
+use crate::cmd_line::QueueKind;
+use short::{DirectedGraph, Dijkstra, Bellman, MinMax, Johnson, FloydWarshall, DagPaths, ShortestPathInfo, Cycle};
+use short::{BidirectionalDijkstra, DeltaStepping, AStar, Heuristic, ZeroHeuristic, LandmarkHeuristic, LandmarkSelection, CoordinateHeuristic, Yen, DFS, NegativeCycleSearch, MinMeanCycle};
+use short::{VertexQueue, LazyBinaryHeap, DialQueue, RadixHeap};
+use short::{DifferenceConstraints, ConstraintSolution, ContractionHierarchy};
+use short::{read_adjacency_multi, read_coordinates, read_constraints, Error};
 
 fn print_distance_result(results: BTreeMap<usize,MinMax<i64>>, display_list: Vec<usize>) {
 
-
-//    trace!("Results {:?}",results);
-//    for i in 0..10 {
-////        trace!("Results {} -> {}",i,results[i]);
- //   }
-    let disp_list_len = display_list.len().clone();
     let mut list_of_vertexes =  display_list;
-    if disp_list_len == 0 {
-        list_of_vertexes = (0..results.len()).collect();
+    if list_of_vertexes.is_empty() {
+        list_of_vertexes = results.keys().cloned().collect();
     }
 
     let mut is_first = true;
     for v in list_of_vertexes {
-        trace!("Checking {}, result {}",v,results[&v]);
-        if v < results.len() {
+        if let Some(result) = results.get(&v) {
+           trace!("Checking {}, result {}",v,result);
            if !is_first {
                print!(",");
             }
-            print!("{}", results[&v]);
+            print!("{}", result);
             is_first = false;
         }
         else {
@@ -62,17 +49,16 @@ fn print_distance_result(results: BTreeMap<usize,MinMax<i64>>, display_list: Vec
 
 
 
-pub fn print_path_results(path_results: BTreeMap<usize,ShortestPathInfo> ) {
+pub fn print_path_results(g: &DirectedGraph, path_results: BTreeMap<usize,ShortestPathInfo> ) {
 
     let num_entries = path_results.len().clone();
     for (starting_vertex, entry_info) in path_results {
 
 
         info!("Printing path results for {} items",num_entries);
-        let mut first=true;
-        let path_string : String = entry_info.path.iter().map( |v| { if first { first=false; format!("{}",v) } else { format!(", {}",v) } } ).collect();
+        let path_string : String = entry_info.path.iter().map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(", ");
 
-        print!("{} => path => {}",starting_vertex,path_string);
+        print!("{} => path => {}",g.vertex_name(starting_vertex),path_string);
         if entry_info.has_negative_cycle {
             print!("... (has negative cycle)");
         }
@@ -80,6 +66,156 @@ pub fn print_path_results(path_results: BTreeMap<usize,ShortestPathInfo> ) {
     }
 }
 
+/// Prints the shortest paths from one source vertex, either as a single line of distances
+/// to each destination or as one line per destination with the path
+fn print_all_pairs_row(g: &DirectedGraph, start: usize, results: &BTreeMap<usize,ShortestPathInfo>, display_list: &[usize], show_paths: bool) {
+
+    let dest_list : Vec<usize> = if display_list.is_empty() { results.keys().cloned().collect() } else { display_list.to_vec() };
+    if show_paths {
+        for dest in dest_list {
+            if let Some(info) = results.get(&dest) {
+                let path_string = info.path.iter().map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(", ");
+                println!("{} -> {} : {} => path => {}",g.vertex_name(start),g.vertex_name(dest),info.distance,path_string);
+            }
+        }
+    }
+    else {
+        let distances = dest_list.iter()
+            .filter_map(|dest| results.get(dest).map(|info| format!("{}",info.distance)))
+            .collect::<Vec<String>>()
+            .join(",");
+        println!("{} => {}",g.vertex_name(start),distances);
+    }
+}
+
+/// Prints the distance (or path) of the shortest of all the shortest paths, or NULL (null) if
+/// there is a negative cycle.  Other errors aren't printed (they are returned by the caller)
+fn print_shortest_shortest_path(g: &DirectedGraph, result: &Result<Option<ShortestPathInfo>, Error>, negative_cycle: Option<Cycle>, show_paths: bool) {
+    match result {
+        Ok(Some(shortest)) => {
+            if show_paths {
+                let names : Vec<String> = shortest.path.iter().map(|v| g.vertex_name(*v)).collect();
+                println!("[{}]",names.join(", "));
+            }
+            else {
+                println!("{}",shortest.distance);
+            }
+        },
+        Ok(None) => println!("No paths between any vertexes"),
+        Err(Error::NegativeCycle) => {
+            info!("Negative cycle found...");
+            if show_paths {
+                println!("null");
+            }
+            else {
+                println!("NULL");
+            }
+            if let Some(cycle) = negative_cycle {
+                println!("Negative cycle: {}",cycle);
+            }
+        },
+        Err(_) => (),
+    }
+}
+
+/// Prints the distance and path found by a search between two vertexes along with the number
+/// of vertexes settled by the search
+fn print_point_to_point_result(g: &DirectedGraph, result: &ShortestPathInfo, settled_count: usize) {
+    let path_string = result.path.iter().map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(", ");
+    println!("Distance: {}",result.distance);
+    println!("Path: {}",path_string);
+    println!("Settled: {}",settled_count);
+}
+
+/// Prints the results from a single source (or set of sources) as either the paths, the
+/// distance and nearest source to each destination, or the list of distances
+fn print_single_source_results(g: &DirectedGraph, results: BTreeMap<usize,MinMax<i64>>, mut path_results: BTreeMap<usize,ShortestPathInfo>, display_list: Vec<usize>, show_paths: bool, show_sources: bool) {
+    if show_paths {
+        print_path_results(g, path_results);
+    }
+    else if show_sources {
+        if !display_list.is_empty() {
+            path_results.retain(|dest, _| display_list.contains(dest));
+        }
+        print_source_results(g, path_results);
+    }
+    else {
+        print_distance_result(results,display_list);
+    }
+}
+
+/// Prints the distance to each destination along with the source its shortest path starts from
+fn print_source_results(g: &DirectedGraph, path_results: BTreeMap<usize,ShortestPathInfo>) {
+    for (dest, entry_info) in path_results {
+        if entry_info.distance == MinMax::Max {
+            println!("{} => {}",g.vertex_name(dest),entry_info.distance);
+        }
+        else {
+            println!("{} => {} from {}",g.vertex_name(dest),entry_info.distance,g.vertex_name(entry_info.source));
+        }
+    }
+}
+
+/// Resolves the starting vertex, or the list of sources with their initial offsets, given on
+/// the command line to vertex ids
+fn find_sources(g: &DirectedGraph, start: &Option<String>, sources: &Option<Vec<(String,i64)>>) -> Result<Vec<(usize,i64)>, Error> {
+    match (start, sources) {
+        (_, Some(list)) => list.iter().map(|(name, offset)| Ok((g.find_vertex(name)?, *offset))).collect(),
+        (Some(name), None) => Ok(vec!((g.find_vertex(name)?, 0))),
+        (None, None) => Ok(vec!()),
+    }
+}
+
+/// Runs Dijkstra from the sources with the given queue and prints the results
+fn run_dijkstra<Q: VertexQueue>(g: &DirectedGraph, mut d: Dijkstra<Q>, source_list: &[(usize,i64)], list: Vec<usize>, allow_negative: bool, show_paths: bool, show_sources: bool) -> Result<(), Error> {
+    d.set_allow_negative(allow_negative);
+    for (id, _v) in g.vertex_iter() {
+        d.initialize_vertex(*id);
+    }
+    // only calculate until the paths to the vertexes being displayed are known
+    d.calculate_shortest_paths_multi(g, source_list, &list)?;
+    let results = d.get_shortest_path_distances();
+    let mut path_results = d.get_shortest_paths();
+    if !list.is_empty() {
+        path_results.retain(|dest, _| list.contains(dest));
+    }
+    if show_paths {
+        print_path_results(g, path_results);
+    }
+    else if show_sources {
+        print_source_results(g, path_results);
+    }
+    else {
+        print_distance_result(results,list);
+    }
+    if !d.unreached_targets().is_empty() {
+        let unreached : Vec<String> = d.unreached_targets().iter().map(|v| g.vertex_name(*v)).collect();
+        println!("Unreached: {}",unreached.join(", "));
+    }
+    Ok(())
+}
+
+/// Resolves the vertex names (labels or ids) given on the command line to vertex ids
+fn find_vertexes(g: &DirectedGraph, names: &Option<Vec<String>>) -> Result<Vec<usize>, Error> {
+    match names {
+        None => Ok(vec!()),
+        Some(list) => list.iter().map(|name| g.find_vertex(name)).collect(),
+    }
+}
+
+/// Maps each error to the exit code reported by the command line
+fn exit_code(err: &Error) -> i32 {
+    match err {
+        Error::Io(_) => 3,
+        Error::Parse { .. } => 4,
+        Error::UnknownVertex(_) | Error::UnknownLabel(_) => 5,
+        Error::NegativeCycle => 6,
+        Error::VertexIdCollision(_) => 7,
+        Error::NegativeEdges(_) => 8,
+        Error::NotAcyclic(_) => 9,
+    }
+}
+
 fn main() {
 
     env_logger::init();
@@ -88,6 +224,34 @@ fn main() {
 
     debug!("The Command Line, {:?}!",cmd_line);
 
+    if let Err(err) = run(&cmd_line) {
+        eprintln!("Error: {}",err);
+        std::process::exit(exit_code(&err));
+    }
+}
+
+fn print_constraint_solution(system: &DifferenceConstraints) -> Result<(), Error> {
+    info!("Staring difference constraints");
+    match system.solve()? {
+        ConstraintSolution::Feasible(values) => {
+            println!("Feasible");
+            for name in system.variables() {
+                println!("{} = {}",name,values[name]);
+            }
+        },
+        ConstraintSolution::Infeasible(constraints) => {
+            let total : i64 = constraints.iter().map(|c| c.bound).sum();
+            println!("Infeasible - these constraints add up to 0 <= {}",total);
+            for constraint in constraints {
+                println!("line {}: {}",constraint.line,constraint);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn run(cmd_line: &CommandArgs) -> Result<(), Error> {
+
     // Create a path to the desired file
     let path = Path::new(&cmd_line.filename);
     let display = path.display();
@@ -95,15 +259,24 @@ fn main() {
 
     // Open the path in read-only mode, returns `io::Result<File>`
     let mut file = match File::open(&path) {
-        Err(why) => panic!("couldn't open {}: {}", display, why),
+        Err(why) => {
+            error!("couldn't open {}: {}", display, why);
+            return Err(Error::Io(why));
+        },
         Ok(file) => file,
     };
 
 
+    // the file holds constraints rather than a graph, which is built from them by the solver
+    if let Some(Commands::Constraints {}) = &cmd_line.command {
+        let system = read_constraints(&mut file)?;
+        return print_constraint_solution(&system);
+    }
+
 	let mut g = DirectedGraph::new();
 
     info!("Starting Reading File");
-    read_adjacency_multi(&mut file, &mut g, cmd_line.skip_first);
+    read_adjacency_multi(&mut file, &mut g, cmd_line.skip_first)?;
 
 //    let add_edge_fn = | s,d,w | g.add_edge(s,d,w) ;
 ////    read_adjacency_multi(&mut file, add_edge_fn);
@@ -112,78 +285,109 @@ fn main() {
 
     match &cmd_line.command {
 
-        Some(Commands::Dijkstra { start, display_list, show_paths }) => {
-            let mut d = Dijkstra::new(*start);
+        Some(Commands::Dijkstra { start, sources, target: Some(target), allow_negative, .. }) => {
+            // clap rejects --sources, --display-list, --show-paths and --queue with --target
+            let start = find_sources(&g, start, sources)?[0].0;
+            let target = g.find_vertex(target)?;
+            let mut b = if *allow_negative { BidirectionalDijkstra::with_allow_negative() } else { BidirectionalDijkstra::new() };
 
-            for (id, _v) in g.vertex_iter() {
-                d.initialize_vertex(id.clone());
+            info!("Staring bidirectional Dijkstra");
+            let result = b.calculate_shortest_path(&g, start, target)?;
+            print_point_to_point_result(&g, &result, b.settled_count());
+        },
+        Some(Commands::Dijkstra { start, sources, display_list, show_paths, target: None, allow_negative, queue }) => {
+            let source_list = find_sources(&g, start, sources)?;
+            let list = find_vertexes(&g, display_list)?;
+            let start = source_list[0].0;
+            match queue {
+                QueueKind::MinHeap => run_dijkstra(&g, Dijkstra::new(start), &source_list, list, *allow_negative, *show_paths, sources.is_some())?,
+                QueueKind::LazyHeap => run_dijkstra(&g, Dijkstra::with_queue(start, LazyBinaryHeap::new()), &source_list, list, *allow_negative, *show_paths, sources.is_some())?,
+                QueueKind::Dial => run_dijkstra(&g, Dijkstra::with_queue(start, DialQueue::new()), &source_list, list, *allow_negative, *show_paths, sources.is_some())?,
+                QueueKind::Radix => run_dijkstra(&g, Dijkstra::with_queue(start, RadixHeap::new()), &source_list, list, *allow_negative, *show_paths, sources.is_some())?,
             }
-            d.calculate_shortest_paths(&g, *start);
-            let results = d.get_shortest_path_distances();
-            let path_results = d.get_shortest_paths();
-            let list = match display_list {
-                None => vec!(),
-                Some(x) => x.clone(),
+        },
+        Some(Commands::Delta { start, display_list, delta, threads }) => {
+            let start = g.find_vertex(start)?;
+            let list = find_vertexes(&g, display_list)?;
+            let mut d = match threads {
+                Some(count) => DeltaStepping::<'_>::with_threads(&g, *count),
+                None => DeltaStepping::<'_>::new(&g),
             };
-            if *show_paths {
-                print_path_results(path_results);
-            }
-            else {
-                print_distance_result(results,list);
-            }
+            d.set_delta(*delta);
 
+            info!("Staring delta-stepping");
+            d.calculate_shortest_paths(start)?;
+            print_distance_result(d.get_shortest_path_distances(),list);
         },
-        Some(Commands::Bellman { start, display_list, show_paths }) => {
-            let mut d = Bellman::new(g.vertex_count());
+        Some(Commands::Bellman { start, sources, display_list, show_paths, queue, no_dag }) => {
+            let source_list = find_sources(&g, start, sources)?;
+            let list = find_vertexes(&g, display_list)?;
+
+            // relaxing the edges in topological order gives the same results in linear time
+            // when the graph has no cycles
+            if !*queue && !*no_dag && g.is_acyclic() {
+                info!("Graph is acyclic, using topological order instead of Bellman");
+                let mut d = DagPaths::new(&g);
+                d.calculate_shortest_paths_multi(&source_list)?;
+                print_single_source_results(&g, d.get_shortest_path_distances(), d.get_shortest_paths(), list, *show_paths, sources.is_some());
+                return Ok(());
+            }
+            let mut d = Bellman::new();
 
             info!("Staring Bellman");
-            d.calculate_shortest_paths(&g, *start);
-            let results = d.get_shortest_path_distances();
-            let path_results = d.get_shortest_paths();
-            let list = match display_list {
-                None => vec!(),
-                Some(x) => x.clone(),
-            };
-            if d.has_negative_cycle() {
-                println!("Negative cycle found...")
-            }
-            if *show_paths {
-                print_path_results(path_results);
+            if *queue {
+                d.calculate_shortest_paths_queue_multi(&g, &source_list)?;
             }
             else {
-                print_distance_result(results,list);
+                d.calculate_shortest_paths_multi(&g, &source_list)?;
+            }
+            if d.has_negative_cycle() {
+                println!("Negative cycle found...");
+                if let Some(cycle) = d.negative_cycle() {
+                    println!("Negative cycle: {}",cycle);
+                }
+            }
+            print_single_source_results(&g, d.get_shortest_path_distances(), d.get_shortest_paths(), list, *show_paths, sources.is_some());
+            if d.has_negative_cycle() {
+                return Err(Error::NegativeCycle);
             }
 
         },
-        Some(Commands::Johnson { display_list, show_paths }) => {
-            // let vertex_list = g.get_vertex_ids();
-            let mut j = Johnson::<'_>::new(&mut g);
+        Some(Commands::Dag { start, sources, display_list, show_paths, longest }) => {
+            let source_list = find_sources(&g, start, sources)?;
+            let list = find_vertexes(&g, display_list)?;
+            let mut d = if *longest { DagPaths::with_longest_paths(&g) } else { DagPaths::new(&g) };
+
+            info!("Staring acyclic graph paths");
+            d.calculate_shortest_paths_multi(&source_list)?;
+            print_single_source_results(&g, d.get_shortest_path_distances(), d.get_shortest_paths(), list, *show_paths, sources.is_some());
+        },
+        Some(Commands::Johnson { display_list, show_paths, all: true, threads }) => {
+            let list = find_vertexes(&g, display_list)?;
+            let mut j = Johnson::<'_>::with_threads(&g, *threads);
+
+            info!("Staring Johnson (all pairs)");
+            let result = j.calculate_shortest_paths_from(&list, |start, results| {
+                print_all_pairs_row(&g, start, &results, &list, *show_paths);
+            });
+            if let Err(Error::NegativeCycle) = result {
+                println!("Negative cycle found...");
+                if let Some(cycle) = j.negative_cycle() {
+                    println!("Negative cycle: {}",cycle);
+                }
+            }
+            info!("Johnson complete");
+            result?;
 
-            let _list = match display_list {
-                None => vec!(),
-                Some(x) => x.clone(),
-            };
+        },
+        Some(Commands::Johnson { display_list: _, show_paths, all: false, threads }) => {
+            let mut j = Johnson::<'_>::with_threads(&g, *threads);
 
             info!("Staring Johnson");
-            if let Some(shortest) = j.find_shortest_shortest_path() {
-                if *show_paths {
-                    println!("{:?}",shortest.path);
-                }
-                else {
-                    println!("{}",shortest.distance);
-                }
-
-            }
-            else {
-                info!("Negative cycle found...");
-                if *show_paths {
-                    println!("null");
-                }
-                else {
-                    println!("NULL");
-                }
-            }
+            let result = j.find_shortest_shortest_path();
+            print_shortest_shortest_path(&g, &result, j.negative_cycle(), *show_paths);
             info!("Johnson complete");
+            result?;
 
             /*
             if j.has_negative_cycle() {
@@ -207,13 +411,156 @@ fn main() {
             */
 
         },
+        Some(Commands::Floyd { display_list, show_paths, all: true }) => {
+            let list = find_vertexes(&g, display_list)?;
+            let mut f = FloydWarshall::new(&g);
+
+            info!("Staring Floyd-Warshall (all pairs)");
+            let result = f.calculate_shortest_paths_from(&list, |start, results| {
+                print_all_pairs_row(&g, start, &results, &list, *show_paths);
+            });
+            if let Err(Error::NegativeCycle) = result {
+                println!("Negative cycle found...");
+                if let Some(cycle) = f.negative_cycle() {
+                    println!("Negative cycle: {}",cycle);
+                }
+            }
+            info!("Floyd-Warshall complete");
+            result?;
+
+        },
+        Some(Commands::Floyd { display_list: _, show_paths, all: false }) => {
+            let mut f = FloydWarshall::new(&g);
+
+            info!("Staring Floyd-Warshall");
+            let result = f.find_shortest_shortest_path();
+            print_shortest_shortest_path(&g, &result, f.negative_cycle(), *show_paths);
+            info!("Floyd-Warshall complete");
+            result?;
+
+        },
+        Some(Commands::Astar { source, target, heuristic, landmarks, coordinates }) => {
+            let source = g.find_vertex(source)?;
+            let target = g.find_vertex(target)?;
+            let heuristic : Box<dyn Heuristic> = match heuristic {
+                HeuristicKind::Zero => Box::new(ZeroHeuristic),
+                HeuristicKind::Landmark => Box::new(LandmarkHeuristic::new(&g, &find_vertexes(&g, landmarks)?)?),
+                HeuristicKind::Coordinate => {
+                    let filename = coordinates.as_deref().unwrap_or_default();
+                    let mut file = File::open(filename)?;
+                    Box::new(CoordinateHeuristic::new(read_coordinates(&mut file, &g)?))
+                },
+            };
+            let mut a = AStar::new(heuristic.as_ref());
+
+            info!("Staring A*");
+            let result = a.calculate_shortest_path(&g, source, target)?;
+            print_point_to_point_result(&g, &result, a.settled_count());
+        },
+        Some(Commands::Kpaths { source, target, k }) => {
+            let source = g.find_vertex(source)?;
+            let target = g.find_vertex(target)?;
+            let mut y = Yen::new(&g);
+
+            info!("Staring Yen k shortest paths");
+            let paths = y.calculate_k_shortest_paths(source, target, *k)?;
+            if paths.is_empty() {
+                println!("No path from {} to {}",g.vertex_name(source),g.vertex_name(target));
+            }
+            for (rank, info) in paths.iter().enumerate() {
+                let path_string = info.path.iter().map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(", ");
+                println!("{}: {} => path => {}",rank + 1,info.distance,path_string);
+            }
+        },
+        Some(Commands::Scc { top }) => {
+            let mut dfs = DFS::new(&g);
+
+            info!("Staring strongly connected components");
+            let scc = dfs.strongly_connected_components()?;
+            let sizes : Vec<String> = scc.components.iter().take(*top).map(|component| component.len().to_string()).collect();
+            println!("Components: {}",scc.components.len());
+            println!("Largest: {}",sizes.join(","));
+            println!("Condensation: {} vertexes, {} edges",scc.condensation.vertex_count(),scc.condensation.edge_count());
+        },
+        Some(Commands::Negcycles { threads }) => {
+            let search = match threads {
+                Some(count) => NegativeCycleSearch::with_threads(&g, *count),
+                None => NegativeCycleSearch::new(&g),
+            };
+
+            info!("Staring negative cycle search");
+            let found = search.find_negative_cycles()?;
+            if found.is_empty() {
+                println!("No negative cycles");
+            }
+            for entry in found {
+                let cycle_string = entry.cycle.vertexes.iter().chain(entry.cycle.vertexes.first()).map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(" -> ");
+                println!("Component of {} vertexes (first {}) => cycle => {} (weight {})",entry.component.len(),g.vertex_name(entry.component[0]),cycle_string,entry.cycle.weight);
+            }
+        },
+        Some(Commands::MinMeanCycle {}) => {
+            let mut karp = MinMeanCycle::new(&g);
+
+            info!("Staring minimum mean cycle");
+            match karp.find_min_mean_cycle()? {
+                Some(cycle) => {
+                    let cycle_string = cycle.vertexes.iter().chain(cycle.vertexes.first()).map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(" -> ");
+                    println!("Mean: {}",cycle.mean_weight());
+                    println!("Cycle: {} (weight {}, {} edges)",cycle_string,cycle.weight,cycle.len());
+                },
+                None => println!("Graph has no cycles"),
+            }
+        },
         Some(Commands::Verify {path}) => {
-            let result = g.verify_path(path.to_vec());
+            let path = path.iter().map(|name| g.find_vertex(name)).collect::<Result<Vec<usize>, Error>>()?;
+            let result = g.verify_path(path);
             match result {
                 Some(weight) => println!("Path is valid and has a weight of {}", weight),
                 None =>  println!("Path is not valid"),
             }
         },
+        Some(Commands::PreprocessCh { output }) => {
+            info!("Staring contraction hierarchy preprocessing");
+            let ch = ContractionHierarchy::build(&g)?;
+            let mut output_file = File::create(output)?;
+            ch.save(&mut output_file)?;
+            println!("Hierarchy: {} vertexes, {} edges, {} shortcuts",ch.vertex_count(),ch.edge_count(),ch.shortcut_count());
+            println!("Saved to {}",output);
+        },
+        Some(Commands::QueryCh { hierarchy, source, target }) => {
+            let source = g.find_vertex(source)?;
+            let target = g.find_vertex(target)?;
+            let mut ch = ContractionHierarchy::load(File::open(hierarchy)?)?;
+
+            info!("Staring contraction hierarchy query");
+            let result = ch.calculate_shortest_path(source, target)?;
+            print_point_to_point_result(&g, &result, ch.settled_count());
+        },
+        Some(Commands::PreprocessAlt { output, selection, count, landmarks }) => {
+            let selection = match selection {
+                LandmarkKind::Farthest => LandmarkSelection::Farthest(*count),
+                LandmarkKind::Random => LandmarkSelection::Random(*count),
+                LandmarkKind::Given => LandmarkSelection::Given(find_vertexes(&g, landmarks)?),
+            };
+            info!("Staring landmark preprocessing");
+            let heuristic = LandmarkHeuristic::with_selection(&g, &selection)?;
+            let mut output_file = File::create(output)?;
+            heuristic.save(&mut output_file)?;
+            let landmark_string = heuristic.landmarks().iter().map(|v| g.vertex_name(*v)).collect::<Vec<String>>().join(", ");
+            println!("Landmarks: {}",landmark_string);
+            println!("Saved to {}",output);
+        },
+        Some(Commands::QueryAlt { tables, source, target }) => {
+            let source = g.find_vertex(source)?;
+            let target = g.find_vertex(target)?;
+            let heuristic = LandmarkHeuristic::load(File::open(tables)?)?;
+            let mut a = AStar::new(&heuristic);
+
+            info!("Staring A* with saved landmarks");
+            let result = a.calculate_shortest_path(&g, source, target)?;
+            print_point_to_point_result(&g, &result, a.settled_count());
+        },
+        Some(Commands::Constraints {}) => unreachable!("constraints are solved before reading a graph"),
         Some(Commands::Print {..}) => {
             println!("Printing Graph...");
             g.print_graph();
@@ -224,6 +571,7 @@ fn main() {
         },
 
     }
+    Ok(())
 }
 
 
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap};
+use std::thread;
+
+use crate::dirgraph::DirectedGraph;
+use crate::graphbuilder::GraphBuilder;
+use crate::bellman::Bellman;
+use crate::cycle::Cycle;
+use crate::dfs::DFS;
+use crate::error::{Error, Result};
+
+use log::{ info, debug, error, trace };
+
+/// A strongly connected component containing a negative cycle
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct ComponentNegativeCycle {
+    /// vertexes of the component (in vertex id order)
+    pub component: Vec<usize>,
+    /// one of the negative cycles of the component (with the edge ids of the graph)
+    pub cycle: Cycle,
+}
+
+/// Finds the negative cycles of a graph by strongly connected component.  A cycle can't leave
+/// its component, so only the components with an edge of negative weight between their own
+/// vertexes need to be checked, each with Bellman-Ford on just the component
+pub struct NegativeCycleSearch<'a> {
+        graph: &'a DirectedGraph,
+        /// number of threads used to check the components (0 uses the available cores)
+        threads: usize,
+}
+
+impl<'a> NegativeCycleSearch<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> NegativeCycleSearch<'a> {
+        NegativeCycleSearch { graph, threads: 1 }
+    }
+
+    /// Creates a search that checks the components on the given number of threads (0 uses the
+    /// number of available cores).  The results are the same as with a single thread
+    pub fn with_threads(graph: &'a DirectedGraph, threads: usize) -> NegativeCycleSearch<'a> {
+        NegativeCycleSearch { graph, threads }
+    }
+
+    /// Returns the number of threads used to check the components
+    pub fn threads(&self) -> usize {
+        if self.threads == 0 {
+            thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+        }
+        else {
+            self.threads
+        }
+    }
+
+    /// Returns each component that has a negative cycle along with one of its cycles, in the
+    /// order of the components (largest first)
+    /// Returns NegativeCycle if a component's cycle could not be recovered
+    pub fn find_negative_cycles(&self) -> Result<Vec<ComponentNegativeCycle>> {
+        let scc = DFS::new(self.graph).strongly_connected_components()?;
+
+        // only components with a negative edge inside them can have a negative cycle
+        let mut candidates = Vec::<&Vec<usize>>::new();
+        for (pos, component) in scc.components.iter().enumerate() {
+            let mut has_negative_edge = false;
+            for v in component {
+                if self.graph.get_outgoing_edges(*v)?.iter().any(|e| e.weight() < 0 && scc.component_of[&e.dest()] == pos) {
+                    has_negative_edge = true;
+                    break;
+                }
+            }
+            if has_negative_edge {
+                candidates.push(component);
+            }
+        }
+        info!("Checking {} of {} components for negative cycles",candidates.len(),scc.components.len());
+
+        let threads = self.threads().min(candidates.len()).max(1);
+        let mut results = if threads == 1 {
+            candidates.iter().enumerate()
+                .map(|(pos, component)| Ok((pos, component_negative_cycle(self.graph, component)?)))
+                .collect::<Result<Vec<_>>>()?
+        }
+        else {
+            info!("Checking components with {} threads", threads);
+            // each thread takes every threads'th component, and the results are then put back
+            // in component order
+            thread::scope(|scope| {
+                let workers : Vec<_> = (0..threads).map(|first| {
+                    let candidates = &candidates;
+                    scope.spawn(move || {
+                        candidates.iter().enumerate().skip(first).step_by(threads)
+                            .map(|(pos, component)| Ok((pos, component_negative_cycle(self.graph, component)?)))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                }).collect();
+                workers.into_iter()
+                    .map(|worker| worker.join().expect("Negative cycle worker thread panicked"))
+                    .collect::<Result<Vec<_>>>()
+            })?.into_iter().flatten().collect::<Vec<_>>()
+        };
+        results.sort_by_key(|(pos, _)| *pos);
+
+        Ok(results.into_iter()
+            .filter_map(|(pos, cycle)| cycle.map(|cycle| ComponentNegativeCycle { component: candidates[pos].clone(), cycle }))
+            .collect())
+    }
+}
+
+// runs Bellman-Ford on the subgraph of the component's vertexes and the edges between them,
+// returning the negative cycle found (if any) with the edge ids of the graph
+fn component_negative_cycle(graph: &DirectedGraph, component: &[usize]) -> Result<Option<Cycle>> {
+    let mut subgraph = DirectedGraph::new();
+    for vertex in component {
+        subgraph.define_vertex(*vertex);
+    }
+    // edge id in the subgraph -> edge id in the graph
+    let mut edge_ids = BTreeMap::<usize,usize>::new();
+    for vertex in component {
+        for e in graph.get_outgoing_edges(*vertex)? {
+            if subgraph.vertex_index(e.dest()).is_some() {
+                if let Some(id) = (&mut subgraph).add_edge(e.source(), e.dest(), e.weight()) {
+                    edge_ids.insert(id, e.id());
+                }
+            }
+        }
+    }
+    trace!("Component starting at {} has {} vertexes and {} edges",component[0],subgraph.vertex_count(),subgraph.edge_count());
+
+    // every vertex of the component can be reached from any other, so a single source finds
+    // any negative cycle in it
+    let mut bellman = Bellman::new();
+    bellman.calculate_shortest_paths_queue(&subgraph, component[0])?;
+    if !bellman.has_negative_cycle() {
+        return Ok(None);
+    }
+    if bellman.negative_cycle().is_none() {
+        // the queue variant stops at the first vertex queued too often, which may not lead back
+        // to the cycle through its predecessors, so retry with every vertex changed in a full pass
+        debug!("Component starting at {} has a negative cycle, recovering it with a full pass",component[0]);
+        bellman.calculate_shortest_paths(&subgraph, component[0])?;
+    }
+    match bellman.negative_cycle() {
+        Some(mut cycle) => {
+            debug!("Component starting at {} has negative cycle {}",component[0],cycle);
+            cycle.edges = cycle.edges.iter().map(|id| edge_ids[id]).collect();
+            Ok(Some(cycle))
+        },
+        None => {
+            error!("Component starting at {} has a negative cycle that could not be recovered",component[0]);
+            Err(Error::NegativeCycle)
+        }
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::negcycle::NegativeCycleSearch;
+
+    // components {1,2,3} (negative cycle), {4,5} (negative edge but no negative cycle),
+    // {6,7} (negative cycle) and {8} with a negative edge to it
+	fn components_graph() -> DirectedGraph {
+		let mut g = DirectedGraph::new();
+		for (source, dest, weight) in [(1,2,1),(2,3,-4),(3,1,2),(3,4,1),(4,5,-1),(5,4,3),(5,6,1),(6,7,-2),(7,6,1),(7,8,-9)] {
+			(&mut g).add_edge(source,dest,weight);
+		}
+		g
+	}
+
+    #[test]
+    fn components_with_cycles() {
+		let g = components_graph();
+        let found = NegativeCycleSearch::new(&g).find_negative_cycles().unwrap();
+        assert_eq!(found.len(),2);
+        assert_eq!(found[0].component,vec!(1,2,3));
+        assert_eq!(found[0].cycle.edges,vec!(1,2,3));
+        assert_eq!(found[0].cycle.weight,-1);
+        assert_eq!(found[1].component,vec!(6,7));
+        assert_eq!(found[1].cycle.vertexes,vec!(6,7));
+        assert_eq!(found[1].cycle.edges,vec!(8,9));
+    }
+
+    #[test]
+    fn negative_edge_without_cycle() {
+		let mut g = DirectedGraph::new();
+		(&mut g).add_edge(4,5,-1);
+		(&mut g).add_edge(5,4,3);
+		(&mut g).add_edge(5,6,-7);
+        assert!(NegativeCycleSearch::new(&g).find_negative_cycles().unwrap().is_empty());
+
+        // lowering the edge back to 4 makes the same component a negative cycle
+		(&mut g).add_edge(5,4,0);
+        let found = NegativeCycleSearch::new(&g).find_negative_cycles().unwrap();
+        assert_eq!(found.len(),1);
+        assert_eq!((found[0].component.clone(),found[0].cycle.weight),(vec!(4,5),-1));
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+		let g = components_graph();
+        let serial = NegativeCycleSearch::with_threads(&g, 1).find_negative_cycles().unwrap();
+        for threads in [0, 2, 5] {
+            let parallel = NegativeCycleSearch::with_threads(&g, threads).find_negative_cycles().unwrap();
+            assert_eq!(parallel,serial);
+        }
+    }
+
+}
@@ -1,16 +1,13 @@
-use std::fs::File;
-use std::io::{BufReader,BufRead};
+use std::io::{BufReader,BufRead,Read};
+use std::collections::{BTreeMap, BTreeSet};
 use regex::Regex;
 use log::{  info , error, debug, /*warn,*/ trace };
 use crate::graphbuilder::GraphBuilder;
+use crate::dirgraph::DirectedGraph;
+use crate::constraints::DifferenceConstraints;
+use crate::error::{Error, Result};
 
 
-#[derive(PartialEq)]
-pub enum VertexOrder {
-    SourceFirst,
-    DestFirst,
-}
-
     
 // Format is 1 line per vertex with a tuple consistenting of destination vertex and weight
 // First line is number of vertexes and number of edges
@@ -19,109 +16,382 @@ pub enum VertexOrder {
 // 1   2,8   3,6
 // 2   1,8  3, 4
 // 3   1,6, 2, 4
-pub fn read_adjacency_multi<F> ( file: & mut File,  mut graph_functions: F, skip_first_line: bool)
-where F: GraphBuilder,
+//
+// Vertexes may also be identified by labels (starting with a letter or _), which are assigned
+// unused vertex ids above all of the numeric ids in the file, in the order they are first seen
+// e.g.
+//
+// a   b,8   c,6
+// b   a,8   c,4
+pub fn read_adjacency_multi<R, F> ( reader: R,  mut graph_functions: F, skip_first_line: bool) -> Result<()>
+where R: Read,
+      F: GraphBuilder,
 {
 
-    //open the file
-    let mut reader = BufReader::new(file);
+    let mut reader = BufReader::new(reader);
 
 	let mut _line_count = 0;
     if skip_first_line {
         let mut line_data = String::new();
-        if let Err(error) = reader.read_line(&mut line_data) {
-            error!("Error reading first line {}",error);
-        }
+        reader.read_line(&mut line_data)?;
         trace!("First line skipped {}",line_data);
         _line_count += 1;	
     }
 
+    // split the line into the vertex and the list of adjacent vertexes/weight pairs
+    let re_vertex = Regex::new(r"^\s*(?P<vertex>\d+|[A-Za-z_][\w.]*)(?P<rest_of_line>.*)$").unwrap();
+    // adjacent vertexes are in the format vertex,weight   - and regex below allows for
+    // whitespace
+    let re_adjacent = Regex::new(r"\s*(?P<vertex>\d+|[A-Za-z_][\w.]*)\s*(,|\s)\s*(?P<weight>-?\d*)").unwrap();
+
+    // the whole file is read before any vertexes are defined, so that the labels can be given
+    // ids above the highest numeric id
+    let mut lines = Vec::<AdjacencyLine>::new();
+    let mut last_numeric_id = 0;
     for line in reader.lines() {
 		_line_count += 1;	
-		let line_data = line.unwrap();
+		let line_data = line?;
         trace!("Proccesing Line {} - ({})",_line_count,line_data);
         if _line_count % 10000 == 0 {
             info!("Proccesing Line {} - ({})",_line_count,line_data);
         }
 
-        // split the line into the vertex and the list of adjacent vertexes/weight pairs
-        //let re_vertex = Regex::new(r"\s*(?P<vertex>\d+)\s+(?P<adjacent_list>.*$)").unwrap();
-        let re_vertex = Regex::new(r"^\s*(?P<vertex>\d+)(?P<rest_of_line>.*)$").unwrap();
-        // adjacent vertexes are in the format vertex,weight   - and regex below allows for
-        // whitespace
+        if line_data.trim().is_empty() {
+            trace!("Line {} is empty - skipping",_line_count);
+            continue;
+        }
+
         if let Some(caps) = re_vertex.captures(&line_data) {
 
-            let text1 = caps.get(1).map_or("", |m| m.as_str());
-            trace!("Text1  = {} caps {:?}",text1,caps);
-            let vertex = text1.parse::<usize>().unwrap();
-            debug!("Reading connectsion for vertex {}",vertex);
+            let vertex_match = caps.name("vertex").unwrap();
+            trace!("Vertex  = {} caps {:?}",vertex_match.as_str(),caps);
+            let vertex = VertexField::parse(vertex_match.as_str(), _line_count, vertex_match.start())?;
 
-            graph_functions.add_vertex(vertex);
-            let re_adjacent = Regex::new(r"\s*(?P<vertex>\d+)\s*(,|\s)\s*(?P<weight>-?\d*)").unwrap();
-            let text2 = caps.get(2).map_or("", |m| m.as_str());
+            let rest_of_line = caps.name("rest_of_line").unwrap();
+            let text2 = rest_of_line.as_str();
             trace!("Adjacency info: {}",text2);
 
-
-            let mut _count =0;
+            let mut adjacent = Vec::<(VertexField,i64)>::new();
             for caps in re_adjacent.captures_iter(text2) {
-                let dest_vertex = caps["vertex"].parse::<usize>().unwrap(); 
-                let weight = caps["weight"].parse::<i64>().unwrap(); 
-                debug!("Adding connection from {} to {} with weight {}",vertex,dest_vertex,weight);
-                if None == graph_functions.add_edge(vertex,dest_vertex,weight) {
-                    error!("Cound not Add..");
+                let dest_match = caps.name("vertex").unwrap();
+                let weight_match = caps.name("weight").unwrap();
+                let dest_vertex = VertexField::parse(dest_match.as_str(), _line_count, rest_of_line.start() + dest_match.start())?;
+                let weight = parse_field::<i64>(weight_match.as_str(), _line_count, rest_of_line.start() + weight_match.start())?;
+                adjacent.push((dest_vertex,weight));
+            }
+            for field in std::iter::once(&vertex).chain(adjacent.iter().map(|(dest, _weight)| dest)) {
+                if let VertexField::Id(id) = field {
+                    last_numeric_id = last_numeric_id.max(*id);
                 }
-                _count += 1;
+            }
+            lines.push(AdjacencyLine { vertex, adjacent });
+        }
+        else {
+            error!("Line {} - No vertex found ({})",_line_count,line_data);
+            let column = line_data.len() - line_data.trim_start().len();
+            return Err(Error::Parse { line: _line_count, column: column + 1, message: "no vertex found".to_string() });
+        }
+    }
 
+    // ids read as numbers and ids assigned to labels, used to detect the two overlapping
+    let mut ids = VertexIds { numeric: BTreeSet::<usize>::new(), labeled: BTreeSet::<usize>::new(), first_label_id: last_numeric_id + 1 };
+    for line in lines {
+        let vertex = ids.resolve(&mut graph_functions, line.vertex)?;
+        debug!("Reading connectsion for vertex {}",vertex);
+        for (dest, weight) in line.adjacent {
+            let dest_vertex = ids.resolve(&mut graph_functions, dest)?;
+            debug!("Adding connection from {} to {} with weight {}",vertex,dest_vertex,weight);
+            if None == graph_functions.add_edge(vertex,dest_vertex,weight) {
+                error!("Cound not Add..");
             }
         }
+    }
+    Ok(())
+}
+
+// a vertex as given in the file, either a numeric id or a label (with its location for errors)
+enum VertexField {
+    Id(usize),
+    Label { label: String, line: usize, offset: usize },
+}
+
+impl VertexField {
+
+    fn parse(text: &str, line: usize, offset: usize) -> Result<VertexField> {
+        if text.starts_with(|c: char| c.is_ascii_digit()) {
+            Ok(VertexField::Id(parse_field::<usize>(text, line, offset)?))
+        }
         else {
-            error!("Line {} - No vertex found ({})",_line_count,line_data)
+            Ok(VertexField::Label { label: text.to_string(), line, offset })
         }
     }
 }
 
-/*  NOT USED -- above can read either one or moer per line and with or without ,
- *
-pub fn read_adjacency_single<F: GraphBuilder, >
-    ( file: & mut File, order: VertexOrder, mut graph_functions: F ) {
+// a line of the adjacency list -- the vertex with its adjacent vertexes and edge weights
+struct AdjacencyLine {
+    vertex: VertexField,
+    adjacent: Vec<(VertexField,i64)>,
+}
 
-    //open the file
-    let mut reader = BufReader::new(file);
+// tracks how the vertex ids in the file were assigned
+struct VertexIds {
+    numeric: BTreeSet<usize>,
+    labeled: BTreeSet<usize>,
+    // lowest id given to a new label
+    first_label_id: usize,
+}
 
-    // read the first line
-    let mut line = String::new();
-    let _len = reader.read_line(&mut line).unwrap();
-    info!("First Input Line is \'{}\'",line);
+impl VertexIds {
 
-    // parse the first line which includes number of vertexes and number of edges
-    let first_line_regex = Regex::new(r"\s*(?P<num_vertex>\d+)\s+(?P<num_edges>\d+)\s+.*$").unwrap();
-    let caps = first_line_regex.captures(&line).unwrap();
-    let _num_vertex = caps["num_vertex"].parse::<usize>().unwrap(); 
-    let _num_edges = caps["num_edges"].parse::<usize>().unwrap(); 
+    // converts a vertex field to its vertex id (defining the vertex if needed), checking that an
+    // id isn't used both as a number and for a label
+    fn resolve<F: GraphBuilder>(&mut self, graph_functions: &mut F, field: VertexField) -> Result<usize> {
+        match field {
+            VertexField::Id(id) => {
+                if self.labeled.contains(&id) {
+                    error!("Vertex {} is already assigned to label",id);
+                    return Err(Error::VertexIdCollision(id));
+                }
+                self.numeric.insert(id);
+                graph_functions.add_vertex(id);
+                Ok(id)
+            },
+            VertexField::Label { label, line, offset } => {
+                let id = match graph_functions.add_labeled_vertex(&label, self.first_label_id) {
+                    Some(id) => id,
+                    None => {
+                        error!("Line {} - label {} can't be used, the graph doesn't support labels",line,label);
+                        return Err(Error::Parse { line, column: offset + 1, message: "vertex labels are not supported".to_string() });
+                    }
+                };
+                if self.numeric.contains(&id) {
+                    error!("Line {} - label {} was assigned vertex {} which is already in use",line,label,id);
+                    return Err(Error::VertexIdCollision(id));
+                }
+                self.labeled.insert(id);
+                Ok(id)
+            },
+        }
+    }
+}
 
-	let mut count = 0;
-    for line in reader.lines() {
-		count += 1;	
-		let line_data = line.unwrap();
-        debug!("Processing {} {}",count, line_data);
-        if count % 50 == 0 {
-            info!("Proccesed {}", count);
-        }
-        let line_regex = Regex::new(r"\s*(?P<v1>\d+)\s+(?P<v2>\d+)\s+(?P<weight>-?\d+)*$").unwrap();
-        trace!("Line is {}",line_data);
-        let caps = line_regex.captures(&line_data).unwrap();
-        trace!("Caps is {:#?}",caps);
-        let vertex1 = caps["v1"].parse::<usize>().unwrap(); 
-        let vertex2 = caps["v1"].parse::<usize>().unwrap(); 
-        let weight = caps["weight"].parse::<i64>().unwrap(); 
-        if order == VertexOrder::SourceFirst {
-            graph_functions.add_edge(vertex1,vertex2, weight);
+// parses a single numeric field, reporting the location (offset is 0 based) on failure
+pub(crate) fn parse_field<T>(text: &str, line: usize, offset: usize) -> Result<T>
+where T: std::str::FromStr,
+      T::Err: std::fmt::Display,
+{
+    text.parse::<T>().map_err(|err| {
+        Error::Parse { line, column: offset + 1, message: format!("invalid value '{}' ({})", text, err) }
+    })
+}
+
+// Format is 1 line per vertex with the vertex (id or label) followed by its x and y coordinates
+// e.g.
+//
+// 1   0.0  3.5
+// b   2    -1
+pub fn read_coordinates<R: Read>( reader: R, graph: &DirectedGraph) -> Result<BTreeMap<usize,(f64,f64)>> {
+
+    let reader = BufReader::new(reader);
+    let re_coordinate = Regex::new(r"^\s*(?P<vertex>\d+|[A-Za-z_][\w.]*)\s+(?P<x>-?[\d.]+)(\s*,\s*|\s+)(?P<y>-?[\d.]+)\s*$").unwrap();
+
+    let mut coordinates = BTreeMap::<usize,(f64,f64)>::new();
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_count = line_index + 1;
+		let line_data = line?;
+        if line_data.trim().is_empty() {
+            continue;
+        }
+        if let Some(caps) = re_coordinate.captures(&line_data) {
+            let vertex = graph.find_vertex(&caps["vertex"])?;
+            let x_match = caps.name("x").unwrap();
+            let y_match = caps.name("y").unwrap();
+            let x = parse_field::<f64>(x_match.as_str(), line_count, x_match.start())?;
+            let y = parse_field::<f64>(y_match.as_str(), line_count, y_match.start())?;
+            trace!("Vertex {} at ({},{})",vertex,x,y);
+            coordinates.insert(vertex,(x,y));
         }
         else {
-            graph_functions.add_edge(vertex2,vertex1, weight);
+            error!("Line {} - No coordinates found ({})",line_count,line_data);
+            let column = line_data.len() - line_data.trim_start().len();
+            return Err(Error::Parse { line: line_count, column: column + 1, message: "expected vertex x y".to_string() });
         }
     }
+    Ok(coordinates)
+}
+
+// Format is 1 line per difference constraint between two variables, using either <= or >=,
+// with optional lines declaring variables (so they get a value even if unconstrained) and
+// comments starting with #
+// e.g.
+//
+// var x1 x2 x3
+// x2 - x1 <= 4
+// x3 - x2 >= -2     # same as x2 - x3 <= 2
+pub fn read_constraints<R: Read>( reader: R) -> Result<DifferenceConstraints> {
 
+    let reader = BufReader::new(reader);
+    let re_variables = Regex::new(r"^\s*var\s+(?P<names>[A-Za-z_][\w.]*(\s+[A-Za-z_][\w.]*)*)\s*$").unwrap();
+    let re_constraint = Regex::new(r"^\s*(?P<left>[A-Za-z_][\w.]*)\s*-\s*(?P<right>[A-Za-z_][\w.]*)\s*(?P<op><=|>=)\s*(?P<bound>-?\d+)\s*$").unwrap();
+
+    let mut system = DifferenceConstraints::new();
+    for (line_index, line) in reader.lines().enumerate() {
+        let line_count = line_index + 1;
+		let line_data = line?;
+        let text = match line_data.find('#') {
+            Some(pos) => &line_data[..pos],
+            None => &line_data[..],
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        if let Some(caps) = re_variables.captures(text) {
+            for name in caps["names"].split_whitespace() {
+                system.add_variable(name);
+            }
+        }
+        else if let Some(caps) = re_constraint.captures(text) {
+            let bound_match = caps.name("bound").unwrap();
+            let bound = parse_field::<i64>(bound_match.as_str(), line_count, bound_match.start())?;
+            trace!("Constraint {} - {} {} {}",&caps["left"],&caps["right"],&caps["op"],bound);
+            if &caps["op"] == "<=" {
+                system.add_constraint(&caps["left"], &caps["right"], bound, line_count);
+            }
+            else {
+                system.add_constraint(&caps["right"], &caps["left"], -bound, line_count);
+            }
+        }
+        else {
+            error!("Line {} - No constraint found ({})",line_count,line_data);
+            let column = line_data.len() - line_data.trim_start().len();
+            return Err(Error::Parse { line: line_count, column: column + 1, message: "expected x - y <= c".to_string() });
+        }
+    }
+    info!("Read {} constraints on {} variables",system.constraints().len(),system.variables().len());
+    Ok(system)
 }
-*/
 
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::error::Error;
+    use crate::parse::{read_adjacency_multi, read_coordinates, read_constraints};
+    use crate::constraints::Constraint;
+
+    // builder that only records the edges, so has no support for labels
+    #[derive(Default)]
+    struct EdgeList {
+        edges: Vec<(usize,usize,i64)>,
+    }
+
+    impl GraphBuilder for &mut EdgeList {
+        fn add_edge(&mut self, source: usize, dest: usize, weight: i64) -> Option<usize> {
+            self.edges.push((source,dest,weight));
+            Some(self.edges.len())
+        }
+        fn add_vertex(&mut self, _id: usize) {}
+    }
+
+    fn parse_error(text: &str, skip_first_line: bool) -> (usize, usize, String) {
+        let mut g = DirectedGraph::new();
+        match read_adjacency_multi(text.as_bytes(), &mut g, skip_first_line) {
+            Err(Error::Parse { line, column, message }) => (line, column, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn adjacency_list() {
+        let mut edges = EdgeList::default();
+        read_adjacency_multi("3 3\n1   2,8   3,6\n\n2 1 8  3, -4\n".as_bytes(), &mut edges, true).unwrap();
+        assert_eq!(edges.edges, vec!((1,2,8),(1,3,6),(2,1,8),(2,3,-4)));
+    }
+
+    #[test]
+    fn bad_weight() {
+        let (line, column, message) = parse_error("1 2,8\n2  3,x\n", false);
+        assert_eq!((line, column), (2, 6));
+        assert!(message.contains("invalid value ''"), "{}", message);
+        // the skipped first line still counts
+        let (line, column, _) = parse_error("2 1\n1 2,8\n2 3,99999999999999999999\n", true);
+        assert_eq!((line, column), (3, 5));
+    }
+
+    #[test]
+    fn no_vertex() {
+        assert_eq!(parse_error("1 2,8\n  -3 1,1\n", false), (2, 3, "no vertex found".to_string()));
+    }
+
+    #[test]
+    fn labels_and_numbers() {
+        // b is seen before the numeric ids 2 and 7, but is given an id above them
+        let mut g = DirectedGraph::new();
+        read_adjacency_multi("1 a,3\n2 1,1  b,2\na 7,5\n".as_bytes(), &mut g, false).unwrap();
+        let (a, b) = (g.vertex_by_label("a").unwrap(), g.vertex_by_label("b").unwrap());
+        assert_eq!((a, b), (8, 9));
+        assert_eq!(g.vertex_ids_by_index(), &[1, 8, 2, 9, 7]);
+        assert_eq!(g.verify_path(vec!(2,1,a,7)), Some(9));
+    }
+
+    #[test]
+    fn labels_unsupported() {
+        let mut edges = EdgeList::default();
+        match read_adjacency_multi("1 2,4\n2 1,3 b,5\n".as_bytes(), &mut edges, false) {
+            Err(Error::Parse { line, column, message }) => {
+                assert_eq!((line, column), (2, 7));
+                assert_eq!(message, "vertex labels are not supported");
+            },
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert_eq!(edges.edges, vec!((1,2,4),(2,1,3)));
+    }
+
+    #[test]
+    fn coordinates() {
+        let mut g = DirectedGraph::new();
+        read_adjacency_multi("1 b,2\nb 3,1\n".as_bytes(), &mut g, false).unwrap();
+        let b = g.vertex_by_label("b").unwrap();
+        let coordinates = read_coordinates("1   0.0  3.5\n\n b  2 ,-1\n3 -1.5, 4\n".as_bytes(), &g).unwrap();
+        assert_eq!(coordinates.into_iter().collect::<Vec<_>>(), vec!((1,(0.0,3.5)),(3,(-1.5,4.0)),(b,(2.0,-1.0))));
+    }
+
+    #[test]
+    fn coordinate_errors() {
+        let mut g = DirectedGraph::new();
+        (&mut g).add_edge(1, 2, 1);
+        let parse_error = |text: &str| match read_coordinates(text.as_bytes(), &g) {
+            Err(Error::Parse { line, column, message }) => (line, column, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(parse_error("1 0 0\n  2 5\n"), (2, 3, "expected vertex x y".to_string()));
+        let (line, column, message) = parse_error("1 0 0\n2  1.5.2 7\n");
+        assert_eq!((line, column), (2, 4));
+        assert!(message.contains("'1.5.2'"), "{}", message);
+        assert!(matches!(read_coordinates("1 0 0\n4 1 1\n".as_bytes(), &g), Err(Error::UnknownVertex(4))));
+        assert!(matches!(read_coordinates("a 0 0\n".as_bytes(), &g), Err(Error::UnknownLabel(_))));
+    }
+
+    #[test]
+    fn constraints() {
+        let text = "# system\nvar x1 x2  x4\nx2 - x1 <= 4\n\n  x3 - x2 >= -2     # same as x2 - x3 <= 2\nx1-x3<=0\n";
+        let system = read_constraints(text.as_bytes()).unwrap();
+        assert_eq!(system.variables(), &["x1","x2","x4","x3"]);
+        let constraint = |left: &str, right: &str, bound: i64, line: usize| Constraint { left: left.to_string(), right: right.to_string(), bound, line };
+        assert_eq!(system.constraints(), &[constraint("x2","x1",4,3), constraint("x2","x3",2,5), constraint("x1","x3",0,6)]);
+    }
+
+    #[test]
+    fn constraint_errors() {
+        let parse_error = |text: &str| match read_constraints(text.as_bytes()) {
+            Err(Error::Parse { line, column, message }) => (line, column, message),
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(parse_error("x - y <= 1\n   x + y <= 2  # sum\n"), (2, 4, "expected x - y <= c".to_string()));
+        let (line, column, message) = parse_error("# big\nx - y >= 99999999999999999999\n");
+        assert_eq!((line, column), (2, 10));
+        assert!(message.contains("99999999999999999999"), "{}", message);
+    }
+}
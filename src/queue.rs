@@ -0,0 +1,459 @@
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::fmt::Debug;
+
+use crate::minmax::{MinMax,MinMax::Value};
+
+/// Priority queue of the vertexes Dijkstra hasn't processed yet, keyed by their current
+/// distance.  Vertexes are identified by their dense index in the graph (see
+/// DirectedGraph::vertex_index).  Vertexes that haven't been reached yet are queued with a
+/// distance of Max and are removed after all of the reachable ones
+pub trait VertexQueue: Debug {
+    /// Adds a vertex to the queue
+    fn insert(&mut self, vertex: usize, distance: MinMax<i64>);
+    /// Lowers the distance of a queued vertex, where predecessor is the vertex the new distance
+    /// is through (which a queue may use to order equal distances)
+    fn decrease(&mut self, vertex: usize, distance: MinMax<i64>, predecessor: Option<usize>);
+    /// Current distance of a queued vertex (None once it has been removed or if never added)
+    fn distance(&self, vertex: usize) -> Option<MinMax<i64>>;
+    /// Removes the vertex with the lowest distance
+    fn pop_min(&mut self) -> Option<(usize, MinMax<i64>)>;
+}
+
+// current distance of each queued vertex by dense index, so the queues can check and update
+// a vertex in constant time
+#[derive(Debug,Clone,Default)]
+struct QueuedDistances {
+    distance: Vec<MinMax<i64>>,
+    queued: Vec<bool>,
+}
+
+impl QueuedDistances {
+    fn set(&mut self, vertex: usize, distance: MinMax<i64>) {
+        if vertex >= self.distance.len() {
+            self.distance.resize(vertex + 1, MinMax::Max);
+            self.queued.resize(vertex + 1, false);
+        }
+        self.distance[vertex] = distance;
+        self.queued[vertex] = true;
+    }
+
+    fn get(&self, vertex: usize) -> Option<MinMax<i64>> {
+        match self.queued.get(vertex) {
+            Some(true) => Some(self.distance[vertex]),
+            _ => None,
+        }
+    }
+
+    fn remove(&mut self, vertex: usize) -> Option<MinMax<i64>> {
+        let distance = self.get(vertex)?;
+        self.queued[vertex] = false;
+        Some(distance)
+    }
+}
+
+// queued vertexes that haven't been reached yet (distance Max), removed in index order once
+// the reachable ones are done
+#[derive(Debug,Clone,Default)]
+struct Unreached {
+    flags: Vec<bool>,
+    /// no vertex below this is unreached
+    first: usize,
+}
+
+impl Unreached {
+    fn insert(&mut self, vertex: usize) {
+        if vertex >= self.flags.len() {
+            self.flags.resize(vertex + 1, false);
+        }
+        self.flags[vertex] = true;
+        self.first = self.first.min(vertex);
+    }
+
+    fn remove(&mut self, vertex: usize) {
+        if let Some(flag) = self.flags.get_mut(vertex) {
+            *flag = false;
+        }
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        while self.first < self.flags.len() {
+            let vertex = self.first;
+            self.first += 1;
+            if self.flags[vertex] {
+                self.flags[vertex] = false;
+                return Some(vertex);
+            }
+        }
+        None
+    }
+}
+
+/// std binary heap where lowering a distance adds another entry for the vertex instead of
+/// moving the existing one, and the out of date entries are skipped when they are removed
+#[derive(Debug,Clone,Default)]
+pub struct LazyBinaryHeap {
+    heap: BinaryHeap<Reverse<(MinMax<i64>, usize)>>,
+    distance: QueuedDistances,
+}
+
+impl LazyBinaryHeap {
+    pub fn new() -> LazyBinaryHeap {
+        LazyBinaryHeap::default()
+    }
+}
+
+impl VertexQueue for LazyBinaryHeap {
+    fn insert(&mut self, vertex: usize, distance: MinMax<i64>) {
+        self.distance.set(vertex, distance);
+        self.heap.push(Reverse((distance, vertex)));
+    }
+
+    fn decrease(&mut self, vertex: usize, distance: MinMax<i64>, _predecessor: Option<usize>) {
+        self.insert(vertex, distance);
+    }
+
+    fn distance(&self, vertex: usize) -> Option<MinMax<i64>> {
+        self.distance.get(vertex)
+    }
+
+    fn pop_min(&mut self) -> Option<(usize, MinMax<i64>)> {
+        while let Some(Reverse((distance, vertex))) = self.heap.pop() {
+            if self.distance.get(vertex) == Some(distance) {
+                self.distance.remove(vertex);
+                return Some((vertex, distance));
+            }
+        }
+        None
+    }
+}
+
+// most buckets a DialQueue uses -- vertexes further than this past the current bucket wait in
+// an overflow list until the buckets are empty
+const DIAL_MAX_BUCKETS: usize = 1 << 20;
+
+/// Dial's bucket queue -- a circular array of buckets, one for each distance from the lowest
+/// queued distance up, scanned in increasing order.  Removing a vertex is O(1) apart from
+/// skipping the empty buckets, so it suits small integer weights.  The number of buckets grows
+/// to the spread of the queued distances (at most the largest edge weight once the sources have
+/// been removed), up to DIAL_MAX_BUCKETS.  Distances lower than the last removed are treated as
+/// equal to it
+#[derive(Debug,Clone,Default)]
+pub struct DialQueue {
+    /// entries (distance, vertex) in the bucket of the distance modulo the number of buckets
+    /// (out of date entries are skipped)
+    buckets: Vec<Vec<(i64, usize)>>,
+    /// number of entries in the buckets
+    count: usize,
+    /// distance of the first bucket that may not be empty -- the buckets hold the distances
+    /// from here up to cursor + number of buckets - 1
+    cursor: i64,
+    /// entries too far past the cursor for the buckets, which are all further than the ones
+    /// in the buckets
+    overflow: Vec<(i64, usize)>,
+    /// lowest distance in the overflow
+    overflow_min: i64,
+    /// set once a vertex has been removed, after which lower distances go in the cursor's bucket
+    removed: bool,
+    distance: QueuedDistances,
+    unreached: Unreached,
+}
+
+impl DialQueue {
+    pub fn new() -> DialQueue {
+        DialQueue::default()
+    }
+
+    fn add_to_bucket(&mut self, vertex: usize, distance: i64) {
+        // until a vertex is removed the buckets can start from any distance (as long as it
+        // isn't past the overflow), afterwards they start from the last distance removed
+        if self.count == 0 {
+            if !self.removed && (self.overflow.is_empty() || distance < self.overflow_min) {
+                self.cursor = distance;
+            }
+        }
+        // only the sources (before anything is removed) can be lower than the cursor
+        else if distance < self.cursor && !self.removed {
+            let entries : Vec<(i64, usize)> = self.buckets.iter_mut().flat_map(std::mem::take).collect();
+            self.count = 0;
+            self.cursor = distance;
+            for entry in entries {
+                self.place(entry);
+            }
+        }
+        self.place((distance, vertex));
+    }
+
+    // puts an entry in its bucket (growing the buckets if needed) or the overflow
+    fn place(&mut self, (distance, vertex): (i64, usize)) {
+        let bucket_distance = distance.max(self.cursor);
+        if !self.overflow.is_empty() && bucket_distance >= self.overflow_min {
+            self.overflow.push((distance, vertex));
+            return;
+        }
+        let offset = bucket_distance.abs_diff(self.cursor);
+        if offset >= self.buckets.len() as u64 {
+            if offset >= DIAL_MAX_BUCKETS as u64 {
+                if self.overflow.is_empty() {
+                    self.overflow_min = bucket_distance;
+                }
+                self.overflow_min = self.overflow_min.min(bucket_distance);
+                self.overflow.push((distance, vertex));
+                return;
+            }
+            self.grow(offset as usize + 1);
+        }
+        let pos = bucket_distance.rem_euclid(self.buckets.len() as i64) as usize;
+        self.buckets[pos].push((distance, vertex));
+        self.count += 1;
+    }
+
+    // moves the current entries to at least len buckets (a power of two)
+    fn grow(&mut self, len: usize) {
+        let entries : Vec<(i64, usize)> = self.buckets.iter_mut().flat_map(std::mem::take).collect();
+        self.buckets = vec![Vec::new(); len.next_power_of_two()];
+        self.count = 0;
+        for (distance, vertex) in entries {
+            if self.distance.get(vertex) == Some(Value(distance)) {
+                self.place((distance, vertex));
+            }
+        }
+    }
+}
+
+impl VertexQueue for DialQueue {
+    fn insert(&mut self, vertex: usize, distance: MinMax<i64>) {
+        self.distance.set(vertex, distance);
+        match distance {
+            Value(distance) => self.add_to_bucket(vertex, distance),
+            _ => self.unreached.insert(vertex),
+        }
+    }
+
+    fn decrease(&mut self, vertex: usize, distance: MinMax<i64>, _predecessor: Option<usize>) {
+        self.unreached.remove(vertex);
+        self.insert(vertex, distance);
+    }
+
+    fn distance(&self, vertex: usize) -> Option<MinMax<i64>> {
+        self.distance.get(vertex)
+    }
+
+    fn pop_min(&mut self) -> Option<(usize, MinMax<i64>)> {
+        loop {
+            while self.count > 0 {
+                let pos = self.cursor.rem_euclid(self.buckets.len() as i64) as usize;
+                while let Some((distance, vertex)) = self.buckets[pos].pop() {
+                    self.count -= 1;
+                    if self.distance.get(vertex) == Some(Value(distance)) {
+                        self.distance.remove(vertex);
+                        self.removed = true;
+                        return Some((vertex, Value(distance)));
+                    }
+                }
+                self.cursor += 1;
+            }
+            if self.overflow.is_empty() {
+                break;
+            }
+            // the buckets are empty, so continue from the lowest distance in the overflow
+            self.cursor = self.cursor.max(self.overflow_min);
+            for (distance, vertex) in std::mem::take(&mut self.overflow) {
+                if self.distance.get(vertex) == Some(Value(distance)) {
+                    self.place((distance, vertex));
+                }
+            }
+        }
+        let vertex = self.unreached.pop_first()?;
+        Some((vertex, self.distance.remove(vertex).unwrap()))
+    }
+}
+
+/// Radix heap -- a monotone priority queue with a bucket for each bit position of the
+/// difference from the last distance removed, so each vertex moves to a lower bucket at most
+/// 64 times.  Distances lower than the last removed are treated as equal to it
+#[derive(Debug,Clone)]
+pub struct RadixHeap {
+    /// bucket i holds the entries whose key differs from last in the highest bit i - 1
+    /// (bucket 0 holds the ones equal to last)
+    buckets: Vec<Vec<(u64, usize)>>,
+    /// key of the last distance removed
+    last: u64,
+    distance: QueuedDistances,
+    unreached: Unreached,
+}
+
+impl RadixHeap {
+    pub fn new() -> RadixHeap {
+        RadixHeap { buckets: vec![Vec::new(); 65], last: 0, distance: QueuedDistances::default(), unreached: Unreached::default() }
+    }
+
+    // maps a distance to an unsigned key in the same order
+    fn key(distance: i64) -> u64 {
+        (distance as u64) ^ (1 << 63)
+    }
+
+    fn bucket(&self, key: u64) -> usize {
+        (64 - (key ^ self.last).leading_zeros()) as usize
+    }
+
+    fn add_to_bucket(&mut self, vertex: usize, key: u64) {
+        let key = key.max(self.last);
+        let pos = self.bucket(key);
+        self.buckets[pos].push((key, vertex));
+    }
+}
+
+impl Default for RadixHeap {
+    fn default() -> Self {
+        RadixHeap::new()
+    }
+}
+
+impl VertexQueue for RadixHeap {
+    fn insert(&mut self, vertex: usize, distance: MinMax<i64>) {
+        self.distance.set(vertex, distance);
+        match distance {
+            Value(distance) => self.add_to_bucket(vertex, RadixHeap::key(distance)),
+            _ => self.unreached.insert(vertex),
+        }
+    }
+
+    fn decrease(&mut self, vertex: usize, distance: MinMax<i64>, _predecessor: Option<usize>) {
+        self.unreached.remove(vertex);
+        self.insert(vertex, distance);
+    }
+
+    fn distance(&self, vertex: usize) -> Option<MinMax<i64>> {
+        self.distance.get(vertex)
+    }
+
+    fn pop_min(&mut self) -> Option<(usize, MinMax<i64>)> {
+        loop {
+            // bucket 0 only has entries equal to last, which are all current minimums
+            while let Some((key, vertex)) = self.buckets[0].pop() {
+                if let Some(Value(distance)) = self.distance.get(vertex) {
+                    if RadixHeap::key(distance).max(self.last) == key {
+                        self.distance.remove(vertex);
+                        return Some((vertex, Value(distance)));
+                    }
+                }
+            }
+            // otherwise the lowest key of the first non-empty bucket becomes last, and that
+            // bucket's entries are spread over the lower buckets
+            let pos = match (1..self.buckets.len()).find(|pos| !self.buckets[*pos].is_empty()) {
+                Some(pos) => pos,
+                None => break,
+            };
+            let entries = std::mem::take(&mut self.buckets[pos]);
+            self.last = entries.iter().map(|(key, _vertex)| *key).min().unwrap();
+            for (key, vertex) in entries {
+                let new_pos = self.bucket(key);
+                self.buckets[new_pos].push((key, vertex));
+            }
+        }
+        let vertex = self.unreached.pop_first()?;
+        Some((vertex, self.distance.remove(vertex).unwrap()))
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use crate::queue::{VertexQueue, LazyBinaryHeap, DialQueue, RadixHeap, DIAL_MAX_BUCKETS};
+    use crate::minmax::{MinMax,MinMax::{Value,Max}};
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::dijkstra::{Dijkstra, VertexInfo};
+    use minheap::MinHeap;
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+    // runs the same operations on the queue as a Dijkstra search would, returning the order
+    // the vertexes are removed in
+    fn check_order<Q: VertexQueue>(queue: &mut Q) -> Vec<(usize, MinMax<i64>)> {
+        for vertex in 1..=6 {
+            queue.insert(vertex, Max);
+        }
+        queue.decrease(2, Value(-3), None);
+        queue.decrease(4, Value(5), None);
+        let mut order = vec!(queue.pop_min().unwrap());
+        assert_eq!(queue.distance(2),None);
+        assert_eq!(queue.distance(4),Some(Value(5)));
+        queue.decrease(4, Value(1), Some(2));
+        queue.decrease(3, Value(1000), Some(2));
+        order.push(queue.pop_min().unwrap());
+        queue.decrease(5, Value(2), Some(4));
+        queue.decrease(3, Value(70), Some(4));
+        while let Some(entry) = queue.pop_min() {
+            order.push(entry);
+        }
+        order
+    }
+
+    #[test]
+    fn queues_agree() {
+        init();
+        let expected = vec!((2,Value(-3)),(4,Value(1)),(5,Value(2)),(3,Value(70)),(1,Max),(6,Max));
+        assert_eq!(check_order(&mut LazyBinaryHeap::new()),expected);
+        assert_eq!(check_order(&mut DialQueue::new()),expected);
+        assert_eq!(check_order(&mut RadixHeap::new()),expected);
+    }
+
+    #[test]
+    fn dial_large_distances() {
+        let mut queue = DialQueue::new();
+        for (vertex, distance) in [(1, 0), (2, 4_000_000_000_000), (3, -1_000_000_000_000), (4, 3), (5, 3)] {
+            queue.insert(vertex, Value(distance));
+        }
+        queue.insert(6, Max);
+        assert_eq!(queue.pop_min(), Some((3, Value(-1_000_000_000_000))));
+        queue.decrease(2, Value(2_000_000), Some(3));
+        queue.decrease(6, Value(2_000_001), Some(3));
+        let mut order = Vec::new();
+        while let Some((vertex, _distance)) = queue.pop_min() {
+            order.push(vertex);
+            assert!(queue.buckets.len() <= DIAL_MAX_BUCKETS);
+        }
+        assert_eq!(order, vec!(1, 5, 4, 2, 6));
+    }
+
+    // distances from several sources (with negative and large offsets) in a random graph
+    // with small and very large weights, using the given queue
+    fn random_graph_distances<Q: VertexQueue>(seed: u64, queue: Q) -> Vec<(usize, MinMax<i64>)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut g = DirectedGraph::new();
+        for v in 1..=300 {
+            g.define_vertex(v);
+        }
+        for _ in 0..1200 {
+            let weight = if rng.gen_bool(0.8) { rng.gen_range(0..=3) } else { rng.gen_range(0..1_000_000_000_000) };
+            (&mut g).add_edge(rng.gen_range(1..=280), rng.gen_range(1..=280), weight);
+        }
+        let mut d = Dijkstra::with_queue(1, queue);
+        for id in g.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        d.calculate_shortest_paths_multi(&g, &[(1, -5), (17, 0), (40, -3_000_000_000_000), (99, 2)], &[]).unwrap();
+        d.get_shortest_path_distances().into_iter().collect()
+    }
+
+    #[test]
+    fn random_graphs() {
+        init();
+        for seed in 0..5 {
+            let expected = random_graph_distances(seed, MinHeap::<VertexInfo>::new());
+            assert!(expected.iter().any(|(_vertex, distance)| *distance == Max));
+            assert_eq!(random_graph_distances(seed, LazyBinaryHeap::new()), expected, "seed {}", seed);
+            assert_eq!(random_graph_distances(seed, DialQueue::new()), expected, "seed {}", seed);
+            assert_eq!(random_graph_distances(seed, RadixHeap::new()), expected, "seed {}", seed);
+        }
+    }
+
+}
@@ -1,11 +1,34 @@
 use crate::minmax::{MinMax};
 
+/// Result of a shortest path calculation between a source and destination vertex
 #[derive(Debug,Clone)]
 pub struct ShortestPathInfo {
+    /// vertex the path starts at
     pub source: usize,
+    /// vertex the path ends at
     pub dest: usize,
+    /// total weight of the path (Max if the dest is unreachable)
     pub distance: MinMax<i64>,
+    /// list of vertexes in the path from source to dest (inclusive)
     pub path: Vec<usize>,
+    /// number of vertexes in the path
     pub path_len: usize,
+    /// set if the path could not be determined due to a negative cycle
     pub has_negative_cycle: bool,
 }
+
+impl ShortestPathInfo {
+
+    /// Result for a dest that can't be reached from the source -- all of the algorithms give a
+    /// Max distance and a path of only the dest vertex
+    pub fn unreachable(source: usize, dest: usize) -> ShortestPathInfo {
+        ShortestPathInfo {
+            source,
+            dest,
+            distance: MinMax::Max,
+            path: vec!(dest),
+            path_len: 1,
+            has_negative_cycle: false,
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{BTreeSet};
+
+use crate::dirgraph::DirectedGraph;
+use crate::dijkstra::Dijkstra;
+
+use log::{ info, debug, warn, trace };
+use crate::minmax::MinMax::Value;
+use crate::shortpathinfo::ShortestPathInfo;
+use crate::error::{Error, Result};
+
+/// K shortest loopless paths between two vertexes using Yen's algorithm.  Each new path is
+/// found by branching off (at a spur vertex) from a prefix of the previous path, with Dijkstra
+/// finding the rest of the path while the edges and vertexes that would repeat an earlier
+/// path are excluded
+pub struct Yen<'a> {
+        graph: &'a DirectedGraph,
+        /// number of spur path searches run by the last calculation
+        spur_searches: usize,
+}
+
+impl<'a> Yen<'a> {
+
+    pub fn new(graph: &'a DirectedGraph) -> Yen<'a> {
+        Yen { graph, spur_searches: 0 }
+    }
+
+    /// Finds up to k shortest loopless paths from source to target in order of distance
+    /// (fewer if there aren't k different paths, none if the target can't be reached)
+    /// Returns NegativeEdges if the graph has negative edge weights
+    pub fn calculate_k_shortest_paths(&mut self, source: usize, target: usize, k: usize) -> Result<Vec<ShortestPathInfo>> {
+        info!("Finding {} shortest paths from {} to {}",k,source,target);
+        for vertex in [source, target] {
+            if self.graph.vertex_index(vertex).is_none() {
+                warn!("Vertex {} is not in the graph",vertex);
+                return Err(Error::UnknownVertex(vertex));
+            }
+        }
+        self.spur_searches = 0;
+        if k == 0 {
+            return Ok(vec!());
+        }
+
+        let mut paths = Vec::<(i64,Vec<usize>)>::new();
+        match self.spur_path(source, target, BTreeSet::new(), BTreeSet::new())? {
+            Some(first) => paths.push(first),
+            None => return Ok(vec!()),
+        }
+
+        // candidate paths ordered by distance (then by vertexes so the order is deterministic)
+        let mut candidates = BTreeSet::<(i64,Vec<usize>)>::new();
+        while paths.len() < k {
+            let previous = paths.last().unwrap().1.clone();
+            for spur_pos in 0..previous.len() - 1 {
+                let spur_vertex = previous[spur_pos];
+                let root = &previous[..=spur_pos];
+
+                // exclude the next edge of each path already found sharing this root, so the
+                // spur path leaves the root differently, and the root vertexes before the spur
+                // so the path stays loopless
+                let mut excluded_edges = BTreeSet::<usize>::new();
+                for (_distance, path) in paths.iter() {
+                    if path.len() > spur_pos + 1 && &path[..=spur_pos] == root {
+                        excluded_edges.extend(self.edges_between(path[spur_pos], path[spur_pos + 1])?);
+                    }
+                }
+                let excluded_vertexes : BTreeSet<usize> = root[..spur_pos].iter().cloned().collect();
+
+                if let Some((spur_distance, spur)) = self.spur_path(spur_vertex, target, excluded_edges, excluded_vertexes)? {
+                    let mut path = root[..spur_pos].to_vec();
+                    path.extend(spur);
+                    let distance = self.path_weight(root)? + spur_distance;
+                    trace!("Candidate path {:?} distance {}",path,distance);
+                    if !paths.iter().any(|(_distance, found)| *found == path) {
+                        candidates.insert((distance, path));
+                    }
+                }
+            }
+
+            match candidates.pop_first() {
+                Some(next) => {
+                    debug!("Path {} is {:?} distance {}",paths.len() + 1,next.1,next.0);
+                    paths.push(next);
+                },
+                None => break,
+            }
+        }
+        info!("Found {} paths with {} spur searches",paths.len(),self.spur_searches);
+
+        Ok(paths.into_iter().map(|(distance, path)| ShortestPathInfo {
+            source,
+            dest: target,
+            distance: Value(distance),
+            path_len: path.len(),
+            path,
+            has_negative_cycle: false,
+        }).collect())
+    }
+
+    /// Number of spur path searches (Dijkstra runs) made by the last calculation
+    pub fn spur_searches(&self) -> usize {
+        self.spur_searches
+    }
+
+    // shortest path from start to target avoiding the excluded edges and vertexes, if there is one
+    fn spur_path(&mut self, start: usize, target: usize, excluded_edges: BTreeSet<usize>, excluded_vertexes: BTreeSet<usize>) -> Result<Option<(i64,Vec<usize>)>> {
+        self.spur_searches += 1;
+        let mut d = Dijkstra::new(start);
+        for id in self.graph.get_vertex_ids() {
+            d.initialize_vertex(id);
+        }
+        d.exclude(excluded_edges, excluded_vertexes);
+        d.calculate_shortest_paths_to(self.graph, start, &[target])?;
+        let paths = d.get_shortest_paths();
+        match paths.get(&target) {
+            Some(ShortestPathInfo { distance: Value(distance), path, .. }) => Ok(Some((*distance, path.clone()))),
+            _ => Ok(None),
+        }
+    }
+
+    // ids of all the edges from source to dest (there may be more than one)
+    fn edges_between(&self, source: usize, dest: usize) -> Result<Vec<usize>> {
+        Ok(self.graph.get_outgoing_edges(source)?
+            .iter()
+            .filter(|e| e.dest() == dest)
+            .map(|e| e.id())
+            .collect())
+    }
+
+    // weight of a path using the lowest weight edge between each pair of vertexes
+    fn path_weight(&self, path: &[usize]) -> Result<i64> {
+        let mut total = 0;
+        for pair in path.windows(2) {
+            total += self.graph.get_outgoing_edges(pair[0])?
+                .iter()
+                .filter(|e| e.dest() == pair[1])
+                .map(|e| e.weight())
+                .min()
+                .unwrap_or(0);
+        }
+        Ok(total)
+    }
+}
+
+// use the attribute below for unit tests
+#[cfg(test)]
+mod tests {
+    use crate::dirgraph::DirectedGraph;
+    use crate::graphbuilder::GraphBuilder;
+    use crate::yen::Yen;
+    use crate::minmax::MinMax::Value;
+    use log::{  info , /*error, debug, warn, trace */ };
+
+    fn init() {
+      let _ = env_logger::builder().is_test(true).try_init();
+      info!("Init {}",module_path!());
+    }
+
+    // graph from the wikipedia example of Yen's algorithm (C=1 D=2 E=3 F=4 G=5 H=6)
+	fn setup_basic(mut g :&mut DirectedGraph) {
+		assert_eq!(g.add_edge(1,2,3),Some(1));
+		assert_eq!(g.add_edge(1,3,2),Some(2));
+		assert_eq!(g.add_edge(2,4,4),Some(3));
+		assert_eq!(g.add_edge(3,2,1),Some(4));
+		assert_eq!(g.add_edge(3,4,2),Some(5));
+		assert_eq!(g.add_edge(3,5,3),Some(6));
+		assert_eq!(g.add_edge(4,5,2),Some(7));
+		assert_eq!(g.add_edge(4,6,1),Some(8));
+		assert_eq!(g.add_edge(5,6,2),Some(9));
+		g.define_vertex(7);
+	}
+
+    #[test]
+    fn basic() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut y = Yen::new(&g);
+        let paths = y.calculate_k_shortest_paths(1,6,3).unwrap();
+        assert_eq!(paths.len(),3);
+        assert_eq!((paths[0].distance,paths[0].path.clone()),(Value(5),vec!(1,3,4,6)));
+        assert_eq!((paths[1].distance,paths[1].path.clone()),(Value(7),vec!(1,3,5,6)));
+        assert_eq!(paths[2].distance,Value(8));
+        for info in paths {
+            assert_eq!(Value(g.verify_path(info.path).unwrap()),info.distance);
+        }
+    }
+
+    #[test]
+    fn fewer_paths() {
+        init();
+		let mut g = DirectedGraph::new();
+        setup_basic(&mut g);
+        let mut y = Yen::new(&g);
+        let paths = y.calculate_k_shortest_paths(1,6,20).unwrap();
+        assert_eq!(paths.len(),7);
+        assert!(paths.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+        assert!(y.calculate_k_shortest_paths(1,7,3).unwrap().is_empty());
+        assert!(y.calculate_k_shortest_paths(1,8,3).is_err());
+        // the graph is left unchanged
+        assert_eq!(g.edge_count(),9);
+    }
+
+}